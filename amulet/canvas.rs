@@ -1,14 +1,35 @@
+use std::cell::{Cell,RefCell};
+use std::cmp::{max,min};
 use std::str;
 use std::vec;
 use std::rc::Rc;
 
+use color::Color;
 use ll::{Key,Style};  // TODO move these somewhere dealing with keys and text and terminal properties
+use ll::{cluster_width,grapheme_clusters,text_width};
 use ll::TerminalInfo;
+use palette::Palette;
+use pen::Pen;
+use termios;
 
 struct CanvasCell {
     dirty: bool,
-    glyph: char,
+    /** One grapheme cluster.  A wide character takes up two cells; the
+     * second is a continuation, with an empty glyph. */
+    glyph: String,
     style: Style,
+    /** The palette entry `style` came from, if any, so it can be looked up
+     * again when the palette changes. */
+    palette_name: Option<Rc<String>>,
+}
+
+fn blank_cell(dirty: bool) -> CanvasCell {
+    return CanvasCell{
+        dirty: dirty,
+        glyph: " ".to_string(),
+        style: Style(),
+        palette_name: None,
+    };
 }
 
 struct CanvasRow {
@@ -18,16 +39,176 @@ struct CanvasRow {
     cells: Vec<CanvasCell>,
 }
 
+impl CanvasRow {
+    fn mark_dirty(&mut self, col: usize) {
+        self.cells[col].dirty = true;
+        if ! self.is_dirty {
+            self.is_dirty = true;
+            self.first_dirty = col;
+            self.last_dirty = col;
+        }
+        else if col < self.first_dirty {
+            self.first_dirty = col;
+        }
+        else if col > self.last_dirty {
+            self.last_dirty = col;
+        }
+    }
+
+    fn is_continuation(&self, col: usize) -> bool {
+        return col < self.cells.len() && self.cells[col].glyph.is_empty();
+    }
+
+    /** Gets ready for the cell at `col` to be overwritten.  If it's half of
+     * a wide character, the other half can't stay on its own, so it becomes
+     * a blank. */
+    fn break_wide(&mut self, col: usize) {
+        if self.is_continuation(col) && col > 0 {
+            self.cells[col - 1].glyph = " ".to_string();
+            self.mark_dirty(col - 1);
+        }
+        if self.is_continuation(col + 1) {
+            self.cells[col + 1].glyph = " ".to_string();
+            self.mark_dirty(col + 1);
+        }
+    }
+
+    /** Works out the dirty range from scratch, after only some of the row
+     * has been painted. */
+    fn recompute_dirty(&mut self) {
+        self.is_dirty = false;
+        self.first_dirty = self.cells.len();
+        self.last_dirty = 0;
+        for col in range(0, self.cells.len()) {
+            if self.cells[col].dirty {
+                self.mark_dirty(col);
+            }
+        }
+    }
+}
+
+/** What's actually on the terminal in one cell, as of the last repaint. */
+#[derive(Clone, PartialEq)]
+struct ScreenCell {
+    glyph: String,
+    style: Style,
+}
+
+fn blank_screen_cell() -> ScreenCell {
+    return ScreenCell{ glyph: " ".to_string(), style: Style() };
+}
+
+/** All the cells of a canvas, shared with every canvas spawned from it. */
+struct CanvasBuffer {
+    /** Where the buffer starts on the screen. */
+    top: usize,
+    left: usize,
+    height: usize,
+    width: usize,
+    /** What we want on the screen. */
+    rows: Vec<CanvasRow>,
+    /** What's on the screen right now, so repainting can skip whatever
+     * hasn't changed.  None where there's no telling. */
+    front: Vec<Vec<Option<ScreenCell>>>,
+    /** Set when the screen can't be trusted at all, so the next repaint
+     * should start over from a blank screen. */
+    redraw_pending: bool,
+    palette: Palette,
+}
+
+impl CanvasBuffer {
+    /** Changes the size of the buffer.  Whatever still fits is kept, but
+     * everything is marked dirty, since there's no telling what the terminal
+     * did to the screen while it was being resized. */
+    fn resize(&mut self, height: usize, width: usize) {
+        self.rows.truncate(height);
+        while self.rows.len() < height {
+            self.rows.push(CanvasRow{
+                is_dirty: false,
+                last_dirty: 0,
+                first_dirty: 0,
+                cells: vec![],
+            });
+        }
+
+        for row in self.rows.iter_mut() {
+            // Don't leave half a wide character at the new edge
+            if row.is_continuation(width) {
+                row.cells[width - 1].glyph = " ".to_string();
+            }
+            row.cells.truncate(width);
+            while row.cells.len() < width {
+                row.cells.push(blank_cell(false));
+            }
+            for col in range(0, width) {
+                row.mark_dirty(col);
+            }
+        }
+
+        // Terminals rewrap, scroll, or clear on resize as they see fit, so
+        // forget everything we thought was on the screen
+        self.front = range(0, height).map(|_row| {
+            range(0, width).map(|_col| None).collect()
+        }).collect();
+        self.redraw_pending = true;
+
+        self.height = height;
+        self.width = width;
+    }
+}
+
+/** Where a spawned canvas sits within the buffer.  Shared with every canvas
+ * spawned from it, so they stay within it even if it's resized later. */
+struct Bounds {
+    start_row: usize,
+    start_col: usize,
+    /** The size the canvas was given, before any clipping. */
+    size: Cell<(usize, usize)>,
+    /** The bounds of the canvas this one was spawned from, unless that was
+     * the original. */
+    parent: Option<Rc<Bounds>>,
+}
+
+impl Bounds {
+    /** Returns where the bottom and right edges end up, as positions just
+     * past the last row and column, once cut off by every canvas this one
+     * is nested inside. */
+    fn limits(&self, buffer_height: usize, buffer_width: usize) -> (usize, usize) {
+        let (max_bottom, max_right) = match self.parent {
+            Some(ref parent) => parent.limits(buffer_height, buffer_width),
+            None => (buffer_height, buffer_width),
+        };
+        let (height, width) = self.size.get();
+        return (
+            min(self.start_row + height, max_bottom),
+            min(self.start_col + width, max_right),
+        );
+    }
+}
+
+/** Somewhere to draw.  Nothing is sent to the terminal until `repaint()`.
+ *
+ * A canvas can `spawn()` smaller ones, which are views onto part of the same
+ * cells: they have their own coordinates and cursor, and can't draw outside
+ * their own rectangle, but repainting any of them shows what the others have
+ * drawn too.
+ */
 pub struct Canvas<'a, 'b> {
     terminfo: &'b TerminalInfo<'b>,
+    buffer: Rc<RefCell<CanvasBuffer>>,
+    /** Where this canvas starts within the buffer. */
     start_row: usize,
     start_col: usize,
     cur_row: usize,
     cur_col: usize,
-    height: usize,
-    width: usize,
+    /** The cell combining characters get added to: the last one written, or
+     * the one before the cursor after a `reposition()`.  None if there isn't
+     * one, e.g. at the start of a line. */
+    last_cell: Option<(usize, usize)>,
+    /** None for the original canvas, which is always exactly as big as the
+     * buffer. */
+    bounds: Option<Rc<Bounds>>,
 
-    rows: Vec<CanvasRow>,
     pub guards: Vec<Box<Drop + 'a>>,
 }
 
@@ -36,63 +217,151 @@ pub fn Canvas<'a, 'b>(terminfo: &'b TerminalInfo<'b>, start_row: usize, start_co
         CanvasRow{
             is_dirty: false,
             last_dirty: 0,
-            first_dirty: 0,
-            cells: range(0, width).map(|_col| {
-                CanvasCell{
-                    dirty: false,
-                    glyph: ' ',
-                    style: Style(),
-                }
-            }).collect(),
+            first_dirty: width,
+            cells: range(0, width).map(|_col| blank_cell(false)).collect(),
         }
     }).collect();
+    // Assume the screen starts out blank, as it does after entering
+    // fullscreen
+    let front = range(0, height).map(|_row| {
+        range(0, width).map(|_col| Some(blank_screen_cell())).collect()
+    }).collect();
+    let buffer = CanvasBuffer{
+        top: start_row,
+        left: start_col,
+        height: height,
+        width: width,
+        rows: rows,
+        front: front,
+        redraw_pending: false,
+        palette: Palette::new(),
+    };
+
     return Canvas{
         terminfo: terminfo,
+        buffer: Rc::new(RefCell::new(buffer)),
 
-        start_row: start_row,
-        start_col: start_col,
+        start_row: 0,
+        start_col: 0,
         cur_row: 0,
         cur_col: 0,
-        height: height,
-        width: width,
+        last_cell: None,
+        bounds: None,
 
-        rows: rows,
         guards: vec![],
     };
 }
 
+/** Returns how much of `length`, starting at `start`, fits within `limit`. */
+fn clip(start: usize, length: usize, limit: usize) -> usize {
+    if start >= limit {
+        return 0;
+    }
+    return min(length, limit - start);
+}
+
+/** Moves the cursor to `col` within a row being painted.  If the cursor is
+ * already on the row, to the left, and everything in between is on the
+ * screen exactly as the pen would draw it now, writing that again is another
+ * way to get there, so offer it to the terminal as an option. */
+fn move_within_row(terminfo: &TerminalInfo, pen: &Pen, row: &CanvasRow, front: &[Option<ScreenCell>], screen_row: usize, screen_left: usize, col: usize) {
+    let mut reprint = None;
+    match terminfo.cursor_position() {
+        Some((cursor_row, cursor_col))
+            if cursor_row == screen_row
+            && cursor_col >= screen_left && cursor_col < screen_left + col
+        => {
+            let mut text = String::new();
+            for between in range(cursor_col - screen_left, col) {
+                let cell = &row.cells[between];
+                match front[between] {
+                    Some(ref shown) if shown.glyph == cell.glyph
+                        && shown.style == cell.style
+                        && shown.style == *pen.style()
+                    => text.push_str(cell.glyph.as_slice()),
+                    _ => break,
+                }
+            }
+            // Starting halfway through a wide character won't do
+            if ! row.is_continuation(cursor_col - screen_left)
+                && text_width(text.as_slice()) == col + screen_left - cursor_col
+            {
+                reprint = Some(text);
+            }
+        }
+        _ => (),
+    }
+
+    terminfo.move_cursor(screen_row, screen_left + col, reprint.as_ref().map(|text| text.as_slice()));
+}
+
 impl<'a, 'b> Canvas<'a, 'b> {
     // -------------------------------------------------------------------------
     // Creation
 
+    /** Creates a canvas for a rectangle within this one.  Coordinates are
+     * relative to this canvas, and a height or width of zero means "as far
+     * as this canvas goes".  Anything that doesn't fit within this canvas is
+     * cut off. */
     pub fn spawn(&self, start_row: usize, start_col: usize, height: usize, width: usize) -> Canvas<'a, 'b> {
-        // TODO verify new height/width will fit?  or don't?  at least verify
-        // h/w aren't negative or zero
+        let (parent_height, parent_width) = self.size();
+        let max_height = clip(start_row, parent_height, parent_height);
+        let max_width = clip(start_col, parent_width, parent_width);
+
         let real_height;
         if height == 0 {
-            real_height = self.height - start_row;
+            real_height = max_height;
         }
         else {
-            real_height = height;
+            real_height = min(height, max_height);
         }
 
         let real_width;
         if width == 0 {
-            real_width = self.width - start_col;
+            real_width = max_width;
         }
         else {
-            real_width = width;
+            real_width = min(width, max_width);
         }
 
-        panic!("todo not done yet");
+        let start_row = self.start_row + min(start_row, parent_height);
+        let start_col = self.start_col + min(start_col, parent_width);
+        return Canvas{
+            terminfo: self.terminfo,
+            buffer: self.buffer.clone(),
+
+            start_row: start_row,
+            start_col: start_col,
+            cur_row: 0,
+            cur_col: 0,
+            last_cell: None,
+            bounds: Some(Rc::new(Bounds{
+                start_row: start_row,
+                start_col: start_col,
+                size: Cell::new((real_height, real_width)),
+                parent: self.bounds.clone(),
+            })),
+
+            guards: vec![],
+        };
     }
 
     // -------------------------------------------------------------------------
     // Accessors
 
-    /** Returns the size of the canvas as (rows, columns). */
+    /** Returns the size of the canvas as (rows, columns).  A spawned canvas
+     * is cut off at the edges of the one it came from, even if either has
+     * been resized since. */
     pub fn size(&self) -> (usize, usize) {
-        return (self.height, self.width);
+        let buffer = self.buffer.borrow();
+        let (bottom, right) = match self.bounds {
+            Some(ref bounds) => bounds.limits(buffer.height, buffer.width),
+            None => (buffer.height, buffer.width),
+        };
+        return (
+            if bottom > self.start_row { bottom - self.start_row } else { 0 },
+            if right > self.start_col { right - self.start_col } else { 0 },
+        );
     }
 
     pub fn position(&self) -> (usize, usize) {
@@ -102,6 +371,57 @@ impl<'a, 'b> Canvas<'a, 'b> {
     pub fn reposition(&mut self, row: usize, col: usize) {
         self.cur_row = row;
         self.cur_col = col;
+        self.last_cell = if col > 0 { Some((row, col - 1)) } else { None };
+    }
+
+    /** Returns a copy of the palette, which is shared with every other
+     * canvas drawing on the same cells. */
+    pub fn palette(&self) -> Palette {
+        return self.buffer.borrow().palette.clone();
+    }
+
+    /** Switches to a different palette.  Everything written with
+     * `namedwrite()`, by this canvas or any related one, takes on its new
+     * style at the next repaint. */
+    pub fn set_palette(&mut self, palette: Palette) {
+        let depth = self.terminfo.color_depth();
+        let mut buffer = self.buffer.borrow_mut();
+        let buffer = &mut *buffer;
+        buffer.palette = palette;
+
+        for row in buffer.rows.iter_mut() {
+            for col in range(0, row.cells.len()) {
+                let style = match row.cells[col].palette_name {
+                    Some(ref name) => buffer.palette.resolve(name.as_slice(), depth),
+                    None => continue,
+                };
+                row.cells[col].style = style;
+                row.mark_dirty(col);
+            }
+        }
+    }
+
+    /** Changes the size of the canvas.  For the original canvas, this
+     * resizes the cells themselves; whatever still fits is kept, but
+     * everything is marked dirty, since there's no telling what the terminal
+     * did to the screen while it was being resized.  A spawned canvas just
+     * covers a different rectangle, still within the one it came from. */
+    pub fn resize(&mut self, height: usize, width: usize) {
+        match self.bounds {
+            Some(ref bounds) => bounds.size.set((height, width)),
+            None => self.buffer.borrow_mut().resize(height, width),
+        }
+        self.clamp_cursor();
+    }
+
+    fn clamp_cursor(&mut self) {
+        let (height, width) = self.size();
+        if self.cur_row >= height {
+            self.cur_row = if height > 0 { height - 1 } else { 0 };
+        }
+        if self.cur_col >= width {
+            self.cur_col = if width > 0 { width - 1 } else { 0 };
+        }
     }
 
 
@@ -111,74 +431,173 @@ impl<'a, 'b> Canvas<'a, 'b> {
     pub fn clear(&mut self) {
         // TODO clearing the screen can be done with a single termcap, but how
         // do i remember that
-        for row in self.rows.iter_mut() {
-            row.is_dirty = true;
-            row.last_dirty = self.width - 1;
-            row.first_dirty = 0;
-            for cell in row.cells.iter_mut() {
-                *cell = CanvasCell{
-                    dirty: true,
-                    glyph: ' ',
-                    style: Style(),
-                };
+        let (height, width) = self.size();
+        let mut buffer = self.buffer.borrow_mut();
+        for row_i in range(0, height) {
+            let row = &mut buffer.rows[self.start_row + row_i];
+            for col in range(self.start_col, self.start_col + width) {
+                // Wide characters straddling the edge get cut in half
+                row.break_wide(col);
+                row.cells[col] = blank_cell(true);
+                row.mark_dirty(col);
             }
         }
     }
 
     pub fn attrwrite(&mut self, s: &str, style: Style) {
-        for glyph in s.chars() {
-            if glyph == '\n' {
+        self.write_cells(s, style, None);
+    }
+
+    /** Writes text in the style the palette gives `name`.  If the name isn't
+     * in the palette, the text gets the default style, until a palette that
+     * does have it comes along. */
+    pub fn namedwrite(&mut self, s: &str, name: &str) {
+        let style = self.buffer.borrow().palette.resolve(name, self.terminfo.color_depth());
+        self.write_cells(s, style, Some(Rc::new(name.to_string())));
+    }
+
+    /** Writes text at the cursor, wrapping at the right edge.  Anything that
+     * runs off the bottom is cut off.
+     *
+     * Each grapheme cluster gets a cell to itself, or two if it's wide.  A
+     * wide character that would be split by the right edge goes on the next
+     * line instead.  Combining characters at the very start of the text are
+     * added to whatever's just before the cursor.  Control characters other
+     * than newlines are dropped. */
+    fn write_cells(&mut self, s: &str, style: Style, palette_name: Option<Rc<String>>) {
+        // Give an anonymous link an id, so it's still one link even if
+        // repaint ends up sending it in several pieces
+        let mut style = style;
+        if style.hyperlink.as_ref().map_or(false, |link| link.id.is_none()) {
+            style.hyperlink = style.hyperlink.take().map(|link| Rc::new(link.with_generated_id()));
+        }
+
+        let (height, width) = self.size();
+        let mut buffer = self.buffer.borrow_mut();
+        for cluster in grapheme_clusters(s).into_iter() {
+            if self.cur_row >= height {
+                break;
+            }
+
+            // \r\n is a single cluster
+            if cluster == "\n" || cluster == "\r\n" {
                 // TODO this probably needs (a) more cases, (b) termcap
                 // influence
                 self.cur_row += 1;
                 self.cur_col = 0;
-
-                if self.cur_row >= self.height {
-                    panic!("TODO");
-                }
-
-                // TODO is there ever anything to write here?
-                return;
+                self.last_cell = None;
+                continue;
             }
 
-            {
-                let row = &mut self.rows[self.cur_row];
-                row.cells[self.cur_col] = CanvasCell{
-                    dirty: true,
-                    glyph: glyph,
-                    style: style.clone(),
-                };
-                row.is_dirty = true;
-                if self.cur_col > row.last_dirty {
-                    row.last_dirty = self.cur_col;
+            let mut glyph = cluster;
+            let mut glyph_width = match cluster_width(cluster) {
+                Some(width) => width,
+                // Any other control character would move the terminal's
+                // cursor, or worse, so it's dropped
+                None => continue,
+            };
+            if glyph_width == 0 {
+                self.combine_with_previous(&mut *buffer, height, width, cluster);
+                continue;
+            }
+            if glyph_width > width {
+                // Can't possibly fit, so draw something that does
+                glyph = " ";
+                glyph_width = 1;
+            }
+            if self.cur_col < width && self.cur_col + glyph_width > width {
+                // Blank out the leftover space and wrap early
+                for col in range(self.cur_col, width) {
+                    self.put_cell(&mut *buffer, col, " ", 1, &style, &palette_name);
                 }
-                if self.cur_col < row.first_dirty {
-                    row.first_dirty = self.cur_col;
+                self.cur_row += 1;
+                self.cur_col = 0;
+                if self.cur_row >= height {
+                    break;
                 }
             }
 
-            self.cur_col += 1;
-            if self.cur_col >= self.width {
+            // The cursor can only be past the right edge if it was put there
+            // with reposition()
+            if self.cur_col < width {
+                let col = self.cur_col;
+                self.put_cell(&mut *buffer, col, glyph, glyph_width, &style, &palette_name);
+                self.last_cell = Some((self.cur_row, col));
+            }
+
+            self.cur_col += glyph_width;
+            if self.cur_col >= width {
                 self.cur_row += 1;
                 self.cur_col = 0;
             }
-            if self.cur_row >= self.height {
-                panic!("TODO");
-            }
         }
     }
 
+    /** Puts a glyph at `col` on the cursor's row, followed by a
+     * continuation if it's wide, and clears out any other wide character
+     * that gets cut in half. */
+    fn put_cell(&self, buffer: &mut CanvasBuffer, col: usize, glyph: &str, glyph_width: usize, style: &Style, palette_name: &Option<Rc<String>>) {
+        let row = &mut buffer.rows[self.start_row + self.cur_row];
+        let col = self.start_col + col;
+        for i in range(col, col + glyph_width) {
+            row.break_wide(i);
+        }
+        for i in range(col, col + glyph_width) {
+            row.cells[i] = CanvasCell{
+                dirty: true,
+                glyph: if i == col { glyph.to_string() } else { String::new() },
+                style: style.clone(),
+                palette_name: palette_name.clone(),
+            };
+            row.mark_dirty(i);
+        }
+    }
+
+    /** Adds a zero-width cluster, like a combining accent, to the last glyph
+     * written, even if the cursor has since wrapped to the next line.  If
+     * there's nothing there, it's dropped. */
+    fn combine_with_previous(&self, buffer: &mut CanvasBuffer, height: usize, width: usize, cluster: &str) {
+        let (last_row, last_col) = match self.last_cell {
+            Some(cell) => cell,
+            None => return,
+        };
+        if last_row >= height || last_col >= width {
+            return;
+        }
+        let row = &mut buffer.rows[self.start_row + last_row];
+        let mut col = self.start_col + last_col;
+        if row.is_continuation(col) && col > self.start_col {
+            col -= 1;
+        }
+        if row.is_continuation(col) {
+            return;
+        }
+        row.cells[col].glyph.push_str(cluster);
+        row.mark_dirty(col);
+    }
+
     pub fn restyle(&mut self, style: Style) {
-        let row = &mut self.rows[self.cur_row];
-        row.cells[self.cur_col].style = style;
+        let (height, width) = self.size();
+        if self.cur_row >= height || self.cur_col >= width {
+            return;
+        }
 
-        // TODO this is basically duplicated from above
-        row.is_dirty = true;
-        if self.cur_col > row.last_dirty {
-            row.last_dirty = self.cur_col;
+        let mut buffer = self.buffer.borrow_mut();
+        let row = &mut buffer.rows[self.start_row + self.cur_row];
+        let col = self.start_col + self.cur_col;
+        // A wide character is styled as a whole
+        let mut first = col;
+        if row.is_continuation(col) && col > 0 {
+            first = col - 1;
+        }
+        let mut last = first;
+        if row.is_continuation(first + 1) {
+            last = first + 1;
         }
-        if self.cur_col < row.first_dirty {
-            row.first_dirty = self.cur_col;
+        for col in range(first, last + 1) {
+            row.cells[col].style = style.clone();
+            row.cells[col].palette_name = None;
+            row.mark_dirty(col);
         }
     }
 
@@ -186,70 +605,194 @@ impl<'a, 'b> Canvas<'a, 'b> {
         self.attrwrite(s, Style());
     }
 
-    pub fn repaint(&mut self) {
-        // TODO wrap this
-        // TODO check for existence of cup?  fallback?
-        //self.terminfo.write_cap2("cup", self.start_col as int, self.start_row as int);
+    /** Sends every change within this canvas since the last repaint to the
+     * terminal, all in one go.  Returns the number of bytes that took.
+     *
+     * Only cells that actually differ from what's on the screen are sent, so
+     * writing the same thing twice, or redrawing something that's only partly
+     * changed, costs little or nothing.
+     *
+     * If the terminal was resized, or the program was suspended and resumed,
+     * this redraws everything instead; see `redraw_all()`. */
+    pub fn repaint(&mut self) -> usize {
+        if self.terminfo.check_resumed() {
+            self.buffer.borrow_mut().redraw_pending = true;
+        }
+        if self.buffer.borrow().redraw_pending {
+            return self.redraw_all();
+        }
 
-        let mut is_bold = false;
-        let mut fg = 0;
+        let (height, width) = self.size();
+        return self.paint(self.start_row, self.start_col, height, width);
+    }
+
+    /** Redraws every cell from scratch, as though nothing were on the screen
+     * at all.  Useful when something else has scribbled over the screen, e.g.
+     * for ^L.  Covers the whole buffer, not just this canvas.  Returns the
+     * number of bytes that took. */
+    pub fn redraw_all(&mut self) -> usize {
+        let (height, width) = {
+            let mut buffer = self.buffer.borrow_mut();
+            buffer.redraw_pending = false;
+            let height = buffer.height;
+            let width = buffer.width;
+
+            // Reset the attributes first, so the cleared screen has the
+            // default background.  Clearing is only safe when the buffer is
+            // the whole screen, since it'd wipe out anything else too; and if
+            // the terminal can't clear, everything gets painted over anyway
+            self.terminfo.exit_attribute_mode();
+            let covers_screen = buffer.top == 0 && buffer.left == 0
+                && height == self.terminfo.height() && width == self.terminfo.width();
+            let cleared = covers_screen && self.terminfo.clear_screen().is_some();
+
+            let buffer = &mut *buffer;
+            for row_i in range(0, height) {
+                let row = &mut buffer.rows[row_i];
+                let front = &mut buffer.front[row_i];
+                for col in range(0, width) {
+                    row.mark_dirty(col);
+                    front[col] = if cleared { Some(blank_screen_cell()) } else { None };
+                }
+            }
+            (height, width)
+        };
 
-        for row_i in range(0, self.height) {
-            let row = &mut self.rows[row_i];
+        return self.paint(0, 0, height, width);
+    }
+
+    /** Paints whatever's changed within the given rectangle of the buffer. */
+    fn paint(&mut self, top: usize, left: usize, height: usize, width: usize) -> usize {
+        // Note that none of the capabilities used here are guaranteed to
+        // exist; if they're missing, the terminal just doesn't get the
+        // formatting.
+
+        // What the terminal is currently drawing with
+        let mut pen = Pen::new();
+
+        let mut buffer = self.buffer.borrow_mut();
+        let buffer = &mut *buffer;
+        let right = left + width;
+
+        // Clearing to the end of the line is much cheaper than writing a
+        // bunch of spaces, but only works if this canvas reaches the right
+        // edge of the screen
+        let can_erase = width > 0
+            && buffer.left + right == self.terminfo.width()
+            && self.terminfo.has_cap("el");
+        let erase_cost = self.terminfo.cap_length("el", &[]).unwrap_or(0);
+        // Without bce, erasing always uses the default background
+        let has_bce = self.terminfo.flag_cap("bce") == Some(true);
+
+        for row_i in range(0, height) {
+            let screen_row = buffer.top + top + row_i;
+            let screen_left = buffer.left;
+            let row = &mut buffer.rows[top + row_i];
+            let front = &mut buffer.front[top + row_i];
             if ! row.is_dirty {
                 continue;
             }
 
-            // TODO the terminal could track its cursor position and optimize this move away
-            self.terminfo.reposition(self.start_col + row.first_dirty, self.start_row + row_i);
-            // TODO with this level of optimization, imo, there should also be a method for forcibly redrawing the entire screen from (presumed) scratch
-            for col in range(row.first_dirty, row.last_dirty + 1) {
-                let cell = &mut row.cells[col];
+            // Only the part of the row within this canvas gets painted
+            let mut first_dirty = max(row.first_dirty, left);
+            let mut last_dirty = min(row.last_dirty + 1, right);
+            if first_dirty >= last_dirty {
+                continue;
+            }
+            // ...but wide characters can only be drawn whole
+            if row.is_continuation(first_dirty) && first_dirty > 0 {
+                first_dirty -= 1;
+            }
+            if row.is_continuation(last_dirty) {
+                last_dirty += 1;
+            }
+
+            // Figure out which cells don't match the screen
+            let mut changed = vec![];
+            for col in range(first_dirty, last_dirty) {
+                let cell = &row.cells[col];
+                let is_changed = match front[col] {
+                    Some(ref shown) => shown.glyph != cell.glyph || shown.style != cell.style,
+                    None => true,
+                };
+                // The second half of a wide character is drawn by drawing the
+                // first
+                if is_changed && row.is_continuation(col) && col > first_dirty {
+                    changed[col - first_dirty - 1] = true;
+                }
+                changed.push(is_changed);
+            }
 
-                // Deal with formatting
-                if cell.style.is_bold && ! is_bold {
-                    self.terminfo.write_cap("bold");
-                    is_bold = true;
+            // See if the row ends in a run of blanks that could be erased
+            // instead
+            let mut paint_to = last_dirty;
+            let mut erase = false;
+            if can_erase && last_dirty == right {
+                let bg = row.cells[right - 1].style.bg_color;
+                let mut blank_from = last_dirty;
+                while blank_from > first_dirty {
+                    let cell = &row.cells[blank_from - 1];
+                    if cell.glyph.as_slice() != " " || cell.style.bg_color != bg
+                        || cell.style.has_attributes_beyond(&Style())
+                        || cell.style.hyperlink.is_some()
+                    {
+                        break;
+                    }
+                    blank_from -= 1;
                 }
-                else if is_bold && ! cell.style.is_bold {
-                    // TODO this resets formatting entirely -- there's no way
-                    // to turn off bold/underline individually  :|
-                    self.terminfo.write_cap("sgr0");
-                    is_bold = false;
+                // Only worth it if it beats writing the blanks that actually
+                // need writing
+                let to_write = changed.slice_from(blank_from - first_dirty).iter()
+                    .filter(|&&is_changed| is_changed).count();
+                if (bg == Color::Default || has_bce) && to_write > erase_cost {
+                    paint_to = blank_from;
+                    erase = true;
                 }
+            }
 
-                if cell.style.fg_color != fg {
-                    fg = cell.style.fg_color;
-
-                    // Replace -1 with an arbitrarily large number, which
-                    // apparently functions as resetting to the default color
-                    // with setaf.  Maybe.
-                    // TODO i am not sure how reliable this is
-                    let actual_fg = match fg {
-                        -1 => 65535,
-                        _ => fg,
-                    };
-                    // TODO what if setaf doesn't exist?  fall back to setf i
-                    // guess, but what's the difference?
-                    self.terminfo.write_cap1("setaf", actual_fg);
+            for col in range(first_dirty, paint_to) {
+                row.cells[col].dirty = false;
+                if ! changed[col - first_dirty] {
+                    continue;
                 }
 
-                self.terminfo.write(cell.glyph.to_string().as_slice());
-                cell.dirty = false;
+                let cell = &row.cells[col];
+                if ! row.is_continuation(col) {
+                    move_within_row(self.terminfo, &pen, &*row, front.as_slice(), screen_row, screen_left, col);
+                    pen.change(self.terminfo, &cell.style);
+                    self.terminfo.write(cell.glyph.as_slice());
+                }
+                front[col] = Some(ScreenCell{ glyph: cell.glyph.clone(), style: cell.style.clone() });
             }
 
-            row.is_dirty = false;
-            row.first_dirty = self.width;
-            row.last_dirty = 0;
+            if erase {
+                move_within_row(self.terminfo, &pen, &*row, front.as_slice(), screen_row, screen_left, paint_to);
+                // The erased cells take on the current background (given bce),
+                // so set it first
+                pen.change(self.terminfo, &row.cells[paint_to].style);
+                self.terminfo.clr_eol();
+                for col in range(paint_to, right) {
+                    let cell = &mut row.cells[col];
+                    cell.dirty = false;
+                    // Not quite what's on the screen, but close enough that
+                    // there's no visible difference
+                    front[col] = Some(ScreenCell{ glyph: cell.glyph.clone(), style: cell.style.clone() });
+                }
+            }
+
+            // Other canvases may still have dirty cells elsewhere in the row
+            row.recompute_dirty();
         }
 
         // Clean up attribute settings when done
-        // TODO optimization possibilities here if we remember the current cursor style -- which we may need to do anyway once we're tracking more than bold
-        if is_bold {
-            self.terminfo.write_cap("sgr0");
-        }
+        // TODO the pen could outlive a single repaint, and skip this
+        pen.change(self.terminfo, &Style());
 
         // TODO move the cursor to its original position if that's not where it is now
+
+        // TODO not much to be done about a write error here, but ignoring it
+        // seems wrong too
+        return self.terminfo.flush().unwrap_or(0);
     }
 
     // -------------------------------------------------------------------------
@@ -264,6 +807,33 @@ impl<'a, 'b> Canvas<'a, 'b> {
         // etc.  it's hilariously sad.
         // TODO should have a timeout after Esc...  et al.?
         // TODO this could probably stand to be broken out a bit
+
+        // Make sure anything written so far is visible before we block
+        self.terminfo.flush();
+
+        // Wait for a keypress, but give up if the terminal is resized
+        // meanwhile.  A resize wakes up the wait, even one that lands between
+        // checking and waiting, so it's noticed promptly.
+        loop {
+            match self.terminfo.check_resize() {
+                Some((rows, cols)) => {
+                    // The cells cover the whole terminal, whichever canvas
+                    // happens to be asking
+                    self.buffer.borrow_mut().resize(rows, cols);
+                    self.clamp_cursor();
+                    return Key::Resize(rows, cols);
+                }
+                None => (),
+            }
+            if self.terminfo.check_resumed() {
+                // Coming back from ^Z; put the screen back the way it was
+                self.redraw_all();
+            }
+            if termios::wait_for_input(self.terminfo.in_fd) {
+                break;
+            }
+        }
+
         let byte = match self.terminfo.in_file.borrow_mut().read_byte() {
             Ok(byte) => byte,
             // TODO how can this actually happen?
@@ -395,3 +965,4 @@ impl<'a, 'b> Canvas<'a, 'b> {
 
 
 }
+
@@ -0,0 +1,450 @@
+/** Methods on TerminalInfo for every string capability that's meant to be
+ * sent to the terminal, one apiece, like blessings has.
+ *
+ * The table below is generated from the capability list in terminfo(5); the
+ * argument counts come from the `#1`, `#2`... in each description.  Keys,
+ * obsolete termcap leftovers, and capabilities that are really data (like
+ * `acsc`) or take string arguments (like `pfkey`) are left out.
+ *
+ * Each method writes the formatted capability and returns None, writing
+ * nothing, if the terminal doesn't have it.
+ */
+
+use ll::TerminalInfo;
+use tparm::Param;
+
+macro_rules! capabilities {
+    ($( $(#[$attr:meta])* fn $method:ident($cap:expr $(, $arg:ident)*); )*) => (
+        impl<'a> TerminalInfo<'a> {
+            $(
+                $(#[$attr])*
+                pub fn $method(&self $(, $arg: isize)*) -> Option<()> {
+                    return self.write_capv($cap, &[$(Param::Number($arg)),*]);
+                }
+            )*
+        }
+    )
+}
+
+capabilities! {
+    /** Back tab. */
+    fn back_tab("cbt");
+    /** Audible signal (bell). */
+    fn bell("bel");
+    /** Carriage return. */
+    fn carriage_return("cr");
+    /** Change region to line #1 to line #2. */
+    fn change_scroll_region("csr", top, bottom);
+    /** Clear all tab stops. */
+    fn clear_all_tabs("tbc");
+    /** Clear screen and home cursor. */
+    fn clear_screen("clear");
+    /** Clear to end of line. */
+    fn clr_eol("el");
+    /** Clear to end of screen. */
+    fn clr_eos("ed");
+    /** Horizontal position #1, absolute. */
+    fn column_address("hpa", col);
+    /** Move to row #1 columns #2. */
+    fn cursor_address("cup", row, col);
+    /** Down one line. */
+    fn cursor_down("cud1");
+    /** Home cursor (if no cup). */
+    fn cursor_home("home");
+    /** Make cursor invisible. */
+    fn cursor_invisible("civis");
+    /** Move left one space. */
+    fn cursor_left("cub1");
+    /** Memory relative cursor addressing, move to row #1 columns #2. */
+    fn cursor_mem_address("mrcup", row, col);
+    /** Make cursor appear normal (undo civis/cvvis). */
+    fn cursor_normal("cnorm");
+    /** Non-destructive space (move right one space). */
+    fn cursor_right("cuf1");
+    /** Last line, first column (if no cup). */
+    fn cursor_to_ll("ll");
+    /** Up one line. */
+    fn cursor_up("cuu1");
+    /** Make cursor very visible. */
+    fn cursor_visible("cvvis");
+    /** Delete character. */
+    fn delete_character("dch1");
+    /** Delete line. */
+    fn delete_line("dl1");
+    /** Disable status line. */
+    fn dis_status_line("dsl");
+    /** Half a line down. */
+    fn down_half_line("hd");
+    /** Start alternate character set. */
+    fn enter_alt_charset_mode("smacs");
+    /** Turn on blinking. */
+    fn enter_blink_mode("blink");
+    /** Turn on bold (extra bright) mode. */
+    fn enter_bold_mode("bold");
+    /** String to start programs using cup. */
+    fn enter_ca_mode("smcup");
+    /** Enter delete mode. */
+    fn enter_delete_mode("smdc");
+    /** Turn on half-bright mode. */
+    fn enter_dim_mode("dim");
+    /** Enter insert mode. */
+    fn enter_insert_mode("smir");
+    /** Turn on blank mode (characters invisible). */
+    fn enter_secure_mode("invis");
+    /** Turn on protected mode. */
+    fn enter_protected_mode("prot");
+    /** Turn on reverse video mode. */
+    fn enter_reverse_mode("rev");
+    /** Begin standout mode. */
+    fn enter_standout_mode("smso");
+    /** Begin underline mode. */
+    fn enter_underline_mode("smul");
+    /** Erase #1 characters. */
+    fn erase_chars("ech", n);
+    /** End alternate character set. */
+    fn exit_alt_charset_mode("rmacs");
+    /** Turn off all attributes. */
+    fn exit_attribute_mode("sgr0");
+    /** Strings to end programs using cup. */
+    fn exit_ca_mode("rmcup");
+    /** End delete mode. */
+    fn exit_delete_mode("rmdc");
+    /** Exit insert mode. */
+    fn exit_insert_mode("rmir");
+    /** Exit standout mode. */
+    fn exit_standout_mode("rmso");
+    /** Exit underline mode. */
+    fn exit_underline_mode("rmul");
+    /** Visible bell (may not move cursor). */
+    fn flash_screen("flash");
+    /** Hardcopy terminal page eject. */
+    fn form_feed("ff");
+    /** Return from status line. */
+    fn from_status_line("fsl");
+    /** Initialization string. */
+    fn init_1string("is1");
+    /** Initialization string. */
+    fn init_2string("is2");
+    /** Initialization string. */
+    fn init_3string("is3");
+    /** Insert character. */
+    fn insert_character("ich1");
+    /** Insert line. */
+    fn insert_line("il1");
+    /** Insert padding after inserted character. */
+    fn insert_padding("ip");
+    /** Leave 'keyboard_transmit' mode. */
+    fn keypad_local("rmkx");
+    /** Enter 'keyboard_transmit' mode. */
+    fn keypad_xmit("smkx");
+    /** Turn off meta mode. */
+    fn meta_off("rmm");
+    /** Turn on meta mode (8th-bit on). */
+    fn meta_on("smm");
+    /** Newline (behave like cr followed by lf). */
+    fn newline("nel");
+    /** Delete #1 characters. */
+    fn parm_dch("dch", n);
+    /** Delete #1 lines. */
+    fn parm_delete_line("dl", n);
+    /** Down #1 lines. */
+    fn parm_down_cursor("cud", n);
+    /** Insert #1 characters. */
+    fn parm_ich("ich", n);
+    /** Scroll forward #1 lines. */
+    fn parm_index("indn", n);
+    /** Insert #1 lines. */
+    fn parm_insert_line("il", n);
+    /** Move #1 characters to the left. */
+    fn parm_left_cursor("cub", n);
+    /** Move #1 characters to the right. */
+    fn parm_right_cursor("cuf", n);
+    /** Scroll back #1 lines. */
+    fn parm_rindex("rin", n);
+    /** Up #1 lines. */
+    fn parm_up_cursor("cuu", n);
+    /** Print contents of screen. */
+    fn print_screen("mc0");
+    /** Turn off printer. */
+    fn prtr_off("mc4");
+    /** Turn on printer. */
+    fn prtr_on("mc5");
+    /** Repeat char #1 #2 times. */
+    fn repeat_char("rep", ch, n);
+    /** Reset string. */
+    fn reset_1string("rs1");
+    /** Reset string. */
+    fn reset_2string("rs2");
+    /** Reset string. */
+    fn reset_3string("rs3");
+    /** Restore cursor to position of last save_cursor. */
+    fn restore_cursor("rc");
+    /** Vertical position #1 absolute. */
+    fn row_address("vpa", row);
+    /** Save current cursor position. */
+    fn save_cursor("sc");
+    /** Scroll text up. */
+    fn scroll_forward("ind");
+    /** Scroll text down. */
+    fn scroll_reverse("ri");
+    /** Define video attributes #1-#9. */
+    fn set_attributes("sgr", standout, underline, reverse, blink, dim, bold, invis, protect, altcharset);
+    /** Set a tab in every row, current columns. */
+    fn set_tab("hts");
+    /** Current window is lines #1-#2 cols #3-#4. */
+    fn set_window("wind", top, bottom, left, right);
+    /** Tab to next 8-space hardware tab stop. */
+    fn tab("ht");
+    /** Move to status line, column #1. */
+    fn to_status_line("tsl", col);
+    /** Underline char and move past it. */
+    fn underline_char("uc");
+    /** Half a line up. */
+    fn up_half_line("hu");
+    /** Turn on printer for #1 bytes. */
+    fn prtr_non("mc5p", n);
+    /** Turn on xon/xoff handshaking. */
+    fn enter_xon_mode("smxon");
+    /** Turn off xon/xoff handshaking. */
+    fn exit_xon_mode("rmxon");
+    /** Turn on automatic margins. */
+    fn enter_am_mode("smam");
+    /** Turn off automatic margins. */
+    fn exit_am_mode("rmam");
+    /** Enable alternate char set. */
+    fn ena_acs("enacs");
+    /** Turn on soft labels. */
+    fn label_on("smln");
+    /** Turn off soft labels. */
+    fn label_off("rmln");
+    /** Send next input char (for ptys). */
+    fn req_for_input("rfi");
+    /** Clear to beginning of line. */
+    fn clr_bol("el1");
+    /** Clear right and left soft margins. */
+    fn clear_margins("mgc");
+    /** Set left soft margin at current column. (ML is not in BSD termcap). */
+    fn set_left_margin("smgl");
+    /** Set right soft margin at current column. */
+    fn set_right_margin("smgr");
+    /** Label format. */
+    fn label_format("fln");
+    /** Set clock, #1 hrs #2 mins #3 secs. */
+    fn set_clock("sclk", hours, minutes, seconds);
+    /** Display clock. */
+    fn display_clock("dclk");
+    /** Remove clock. */
+    fn remove_clock("rmclk");
+    /** Define a window #1 from #2,#3 to #4,#5. */
+    fn create_window("cwin", p1, p2, p3, p4, p5);
+    /** Go to window #1. */
+    fn goto_window("wingo", window);
+    /** Hang-up phone. */
+    fn hangup("hup");
+    /** Select touch tone dialing. */
+    fn tone("tone");
+    /** Select pulse dialing. */
+    fn pulse("pulse");
+    /** Flash switch hook. */
+    fn flash_hook("hook");
+    /** Pause for 2-3 seconds. */
+    fn fixed_pause("pause");
+    /** Wait for dial-tone. */
+    fn wait_tone("wait");
+    /** Set default pair to its original value. */
+    fn orig_pair("op");
+    /** Set all color pairs to the original ones. */
+    fn orig_colors("oc");
+    /** Initialize color #1 to (#2,#3,#4). */
+    fn initialize_color("initc", color, r, g, b);
+    /** Initialize color pair #1 to fg=(#2,#3,#4), bg=(#5,#6,#7). */
+    fn initialize_pair("initp", pair, fg_r, fg_g, fg_b, bg_r, bg_g, bg_b);
+    /** Set current color pair to #1. */
+    fn set_color_pair("scp", pair);
+    /** Set foreground color #1. */
+    fn set_foreground("setf", color);
+    /** Set background color #1. */
+    fn set_background("setb", color);
+    /** Change number of characters per inch to #1. */
+    fn change_char_pitch("cpi", n);
+    /** Change number of lines per inch to #1. */
+    fn change_line_pitch("lpi", n);
+    /** Change horizontal resolution to #1. */
+    fn change_res_horz("chr", n);
+    /** Change vertical resolution to #1. */
+    fn change_res_vert("cvr", n);
+    /** Define a character #1, #2 dots wide, descender #3. */
+    fn define_char("defc", p1, p2, p3);
+    /** Enter double-wide mode. */
+    fn enter_doublewide_mode("swidm");
+    /** Enter draft-quality mode. */
+    fn enter_draft_quality("sdrfq");
+    /** Enter italic mode. */
+    fn enter_italics_mode("sitm");
+    /** Start leftward carriage motion. */
+    fn enter_leftward_mode("slm");
+    /** Start micro-motion mode. */
+    fn enter_micro_mode("smicm");
+    /** Enter NLQ mode. */
+    fn enter_near_letter_quality("snlq");
+    /** Enter normal-quality mode. */
+    fn enter_normal_quality("snrmq");
+    /** Enter shadow-print mode. */
+    fn enter_shadow_mode("sshm");
+    /** Enter subscript mode. */
+    fn enter_subscript_mode("ssubm");
+    /** Enter superscript mode. */
+    fn enter_superscript_mode("ssupm");
+    /** Start upward carriage motion. */
+    fn enter_upward_mode("sum");
+    /** End double-wide mode. */
+    fn exit_doublewide_mode("rwidm");
+    /** End italic mode. */
+    fn exit_italics_mode("ritm");
+    /** End left-motion mode. */
+    fn exit_leftward_mode("rlm");
+    /** End micro-motion mode. */
+    fn exit_micro_mode("rmicm");
+    /** End shadow-print mode. */
+    fn exit_shadow_mode("rshm");
+    /** End subscript mode. */
+    fn exit_subscript_mode("rsubm");
+    /** End superscript mode. */
+    fn exit_superscript_mode("rsupm");
+    /** End reverse character motion. */
+    fn exit_upward_mode("rum");
+    /** Like column_address in micro mode. */
+    fn micro_column_address("mhpa", col);
+    /** Like cursor_down in micro mode. */
+    fn micro_down("mcud1");
+    /** Like cursor_left in micro mode. */
+    fn micro_left("mcub1");
+    /** Like cursor_right in micro mode. */
+    fn micro_right("mcuf1");
+    /** Like row_address #1 in micro mode. */
+    fn micro_row_address("mvpa", row);
+    /** Like cursor_up in micro mode. */
+    fn micro_up("mcuu1");
+    /** Like parm_down_cursor in micro mode. */
+    fn parm_down_micro("mcud", n);
+    /** Like parm_left_cursor in micro mode. */
+    fn parm_left_micro("mcub", n);
+    /** Like parm_right_cursor in micro mode. */
+    fn parm_right_micro("mcuf", n);
+    /** Like parm_up_cursor in micro mode. */
+    fn parm_up_micro("mcuu", n);
+    /** Select character set, #1. */
+    fn select_char_set("scs", charset);
+    /** Set bottom margin at current line. */
+    fn set_bottom_margin("smgb");
+    /** Set bottom margin at line #1 or (if smgtp is not given) #2 lines from bottom. */
+    fn set_bottom_margin_parm("smgbp", row, lines);
+    /** Set left (right) margin at column #1. */
+    fn set_left_margin_parm("smglp", col);
+    /** Set right margin at column #1. */
+    fn set_right_margin_parm("smgrp", col);
+    /** Set top margin at current line. */
+    fn set_top_margin("smgt");
+    /** Set top (bottom) margin at row #1. */
+    fn set_top_margin_parm("smgtp", row);
+    /** Start printing bit image graphics. */
+    fn start_bit_image("sbim");
+    /** Start character set definition #1, with #2 characters in the set. */
+    fn start_char_set_def("scsd", p1, p2);
+    /** Stop printing bit image graphics. */
+    fn stop_bit_image("rbim");
+    /** End definition of character set #1. */
+    fn stop_char_set_def("rcsd", p1);
+    /** Produce #1'th item from list of character set names. */
+    fn char_set_names("csnm", n);
+    /** Request mouse position. */
+    fn req_mouse_pos("reqmp");
+    /** Curses should get button events, parameter #1 not documented. */
+    fn get_mouse("getm", p1);
+    /** Set foreground color to #1, using ANSI escape. */
+    fn set_a_foreground("setaf", color);
+    /** Set background color to #1, using ANSI escape. */
+    fn set_a_background("setab", color);
+    /** Shift to codeset 0 (EUC set 0, ASCII). */
+    fn set0_des_seq("s0ds");
+    /** Shift to codeset 1. */
+    fn set1_des_seq("s1ds");
+    /** Shift to codeset 2. */
+    fn set2_des_seq("s2ds");
+    /** Shift to codeset 3. */
+    fn set3_des_seq("s3ds");
+    /** Set both left and right margins to #1, #2. (ML is not in BSD termcap). */
+    fn set_lr_margin("smglr", left, right);
+    /** Sets both top and bottom margins to #1, #2. */
+    fn set_tb_margin("smgtb", top, bottom);
+    /** Repeat bit image cell #1 #2 times. */
+    fn bit_image_repeat("birep", cell, n);
+    /** Move to next row of the bit image. */
+    fn bit_image_newline("binel");
+    /** Move to beginning of same row. */
+    fn bit_image_carriage_return("bicr");
+    /** Give name for color #1. */
+    fn color_names("colornm", color);
+    /** Define rectangular bit image region. */
+    fn define_bit_image_region("defbi");
+    /** End a bit-image region. */
+    fn end_bit_image_region("endbi");
+    /** Change to ribbon color #1. */
+    fn set_color_band("setcolor", color);
+    /** Set page length to #1 lines. */
+    fn set_page_length("slines", lines);
+    /** Display PC character #1. */
+    fn display_pc_char("dispc", ch);
+    /** Enter PC character display mode. */
+    fn enter_pc_charset_mode("smpch");
+    /** Exit PC character display mode. */
+    fn exit_pc_charset_mode("rmpch");
+    /** Enter PC scancode mode. */
+    fn enter_scancode_mode("smsc");
+    /** Exit PC scancode mode. */
+    fn exit_scancode_mode("rmsc");
+    /** PC terminal options. */
+    fn pc_term_options("pctrm");
+    /** Escape for scancode emulation. */
+    fn scancode_escape("scesc");
+    /** Alternate escape for scancode emulation. */
+    fn alt_scancode_esc("scesa");
+    /** Enter horizontal highlight mode. */
+    fn enter_horizontal_hl_mode("ehhlm");
+    /** Enter left highlight mode. */
+    fn enter_left_hl_mode("elhlm");
+    /** Enter low highlight mode. */
+    fn enter_low_hl_mode("elohlm");
+    /** Enter right highlight mode. */
+    fn enter_right_hl_mode("erhlm");
+    /** Enter top highlight mode. */
+    fn enter_top_hl_mode("ethlm");
+    /** Enter vertical highlight mode. */
+    fn enter_vertical_hl_mode("evhlm");
+    /** Define second set of video attributes #1-#6. */
+    fn set_a_attributes("sgr1", p1, p2, p3, p4, p5, p6);
+    /** Set page length to #1 hundredth of an inch (some implementations use sL for termcap). */
+    fn set_pglen_inch("slength", n);
+}
+
+// Extended capabilities.  These aren't in terminfo(5) at all; they're
+// conventions started by tmux and friends, so plenty of terminals that could
+// do these things don't say so.
+capabilities! {
+    /** Set cursor style #1: 0 or 1 for a blinking block, 2 for a steady
+     * block, 3 and 4 for underlines, 5 and 6 for bars. */
+    fn set_cursor_style("Ss", style);
+    /** Reset cursor style to the terminal's default. */
+    fn reset_cursor_style("Se");
+    /** Set underline style #1: 0 for none, 1 for single, 2 for double, 3 for
+     * curly, 4 for dotted, 5 for dashed. */
+    fn set_underline_style("Smulx", style);
+    /** Set underline color #1, as 24-bit RGB. */
+    fn set_underline_color("Setulc", color);
+    /** Turn on strikethrough mode. */
+    fn enter_strikethrough_mode("smxx");
+    /** Turn off strikethrough mode. */
+    fn exit_strikethrough_mode("rmxx");
+    /** Turn on overline mode. */
+    fn enter_overline_mode("Smol");
+}
@@ -0,0 +1,383 @@
+/** Colors, in all the varieties terminals have grown over the years. */
+
+use std::ascii::AsciiExt;
+use std::collections::HashMap;
+use std::fmt;
+use std::num::Float;
+
+/** A text or background color.
+ *
+ * The named colors are the sixteen ANSI ones, which every color terminal has
+ * in some form, though what they actually look like is up to the user's
+ * configuration.  `Indexed` picks from the terminal's palette, usually 256
+ * colors; `Rgb` is 24-bit "truecolor", which only some terminals understand.
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Show)]
+pub enum Color {
+    /** Whatever the terminal uses when nobody's asked for anything. */
+    Default,
+
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    /** Returns the palette index for this color, or None if it's the default
+     * color or a truecolor one.  The named colors are 0 through 15. */
+    pub fn index(&self) -> Option<u8> {
+        return match *self {
+            Color::Default => None,
+            Color::Black => Some(0),
+            Color::Red => Some(1),
+            Color::Green => Some(2),
+            Color::Yellow => Some(3),
+            Color::Blue => Some(4),
+            Color::Magenta => Some(5),
+            Color::Cyan => Some(6),
+            Color::White => Some(7),
+            Color::BrightBlack => Some(8),
+            Color::BrightRed => Some(9),
+            Color::BrightGreen => Some(10),
+            Color::BrightYellow => Some(11),
+            Color::BrightBlue => Some(12),
+            Color::BrightMagenta => Some(13),
+            Color::BrightCyan => Some(14),
+            Color::BrightWhite => Some(15),
+            Color::Indexed(n) => Some(n),
+            Color::Rgb(..) => None,
+        };
+    }
+
+    /** Parses a color name, `#rrggbb`, or a palette index.  Names are the
+     * variants above in lowercase, with an underscore, space, or hyphen
+     * between words: `bright_red`, `bright red`.  Returns None for anything
+     * else. */
+    pub fn parse(s: &str) -> Option<Color> {
+        let s = s.trim();
+        if s.starts_with("#") {
+            return parse_hex(s.slice_from(1));
+        }
+        match s.parse::<u8>() {
+            Some(n) => return Some(Color::Indexed(n)),
+            None => (),
+        }
+
+        let name = s.to_ascii_lowercase().replace(" ", "_").replace("-", "_");
+        return match name.as_slice() {
+            "default" => Some(Color::Default),
+            "black" => Some(Color::Black),
+            "red" => Some(Color::Red),
+            "green" => Some(Color::Green),
+            "yellow" => Some(Color::Yellow),
+            "blue" => Some(Color::Blue),
+            "magenta" => Some(Color::Magenta),
+            "cyan" => Some(Color::Cyan),
+            "white" => Some(Color::White),
+            "bright_black" => Some(Color::BrightBlack),
+            "bright_red" => Some(Color::BrightRed),
+            "bright_green" => Some(Color::BrightGreen),
+            "bright_yellow" => Some(Color::BrightYellow),
+            "bright_blue" => Some(Color::BrightBlue),
+            "bright_magenta" => Some(Color::BrightMagenta),
+            "bright_cyan" => Some(Color::BrightCyan),
+            "bright_white" => Some(Color::BrightWhite),
+            _ => None,
+        };
+    }
+}
+
+/** Writes the color the way `Color::parse()` reads it. */
+impl fmt::String for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Color::Default => "default",
+            Color::Black => "black",
+            Color::Red => "red",
+            Color::Green => "green",
+            Color::Yellow => "yellow",
+            Color::Blue => "blue",
+            Color::Magenta => "magenta",
+            Color::Cyan => "cyan",
+            Color::White => "white",
+            Color::BrightBlack => "bright_black",
+            Color::BrightRed => "bright_red",
+            Color::BrightGreen => "bright_green",
+            Color::BrightYellow => "bright_yellow",
+            Color::BrightBlue => "bright_blue",
+            Color::BrightMagenta => "bright_magenta",
+            Color::BrightCyan => "bright_cyan",
+            Color::BrightWhite => "bright_white",
+            Color::Indexed(n) => return write!(f, "{}", n),
+            Color::Rgb(r, g, b) => return write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+        };
+        return write!(f, "{}", name);
+    }
+}
+
+/** Parses the `rrggbb` part of a hex color. */
+fn parse_hex(digits: &str) -> Option<Color> {
+    let digits = digits.as_bytes();
+    if digits.len() != 6 {
+        return None;
+    }
+
+    let mut channels = [0u8; 3];
+    for i in range(0, 3) {
+        match (hex_digit(digits[i * 2]), hex_digit(digits[i * 2 + 1])) {
+            (Some(high), Some(low)) => channels[i] = high * 16 + low,
+            _ => return None,
+        }
+    }
+    return Some(Color::Rgb(channels[0], channels[1], channels[2]));
+}
+
+fn hex_digit(byte: u8) -> Option<u8> {
+    return match byte {
+        b'0'...b'9' => Some(byte - b'0'),
+        b'a'...b'f' => Some(byte - b'a' + 10),
+        b'A'...b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    };
+}
+
+/** Roughly how many colors a terminal can show.  Ordered, so e.g. anything at
+ * least `Colors256` can do everything `Colors16` can. */
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Show)]
+pub enum ColorDepth {
+    Monochrome,
+    /** The ANSI colors.  Terminals with only eight are close enough. */
+    Colors16,
+    Colors256,
+    Truecolor,
+}
+
+
+
+// -----------------------------------------------------------------------------
+// Downsampling
+
+/** How to decide which of the terminal's colors is closest to one it can't
+ * show. */
+#[derive(Clone, Copy, PartialEq, Show)]
+pub enum DistanceMetric {
+    /** Straight-line distance in RGB.  Cheap, but treats all three channels
+     * as equally important, which your eyes don't. */
+    Euclidean,
+    /** RGB distance weighted by how red the colors are; a well-known cheap
+     * approximation of perceptual distance.  The default. */
+    Redmean,
+    /** Distance in CIE L*a*b*, i.e. CIE76 delta E.  The most accurate of the
+     * three, and the slowest, though with caching that hardly matters. */
+    Cie76,
+}
+
+impl DistanceMetric {
+    fn distance(&self, a: (u8, u8, u8), b: (u8, u8, u8)) -> f64 {
+        let (r1, g1, b1) = a;
+        let (r2, g2, b2) = b;
+        return match *self {
+            DistanceMetric::Euclidean => {
+                let dr = r1 as f64 - r2 as f64;
+                let dg = g1 as f64 - g2 as f64;
+                let db = b1 as f64 - b2 as f64;
+                dr * dr + dg * dg + db * db
+            }
+            DistanceMetric::Redmean => {
+                let rmean = (r1 as f64 + r2 as f64) / 2.0;
+                let dr = r1 as f64 - r2 as f64;
+                let dg = g1 as f64 - g2 as f64;
+                let db = b1 as f64 - b2 as f64;
+                (2.0 + rmean / 256.0) * dr * dr + 4.0 * dg * dg + (2.0 + (255.0 - rmean) / 256.0) * db * db
+            }
+            DistanceMetric::Cie76 => {
+                let (l1, a1, b1) = rgb_to_lab(a);
+                let (l2, a2, b2) = rgb_to_lab(b);
+                let dl = l1 - l2;
+                let da = a1 - a2;
+                let db = b1 - b2;
+                dl * dl + da * da + db * db
+            }
+        };
+    }
+}
+
+/** Converts an sRGB color to CIE L*a*b*, assuming a D65 white point. */
+fn rgb_to_lab(rgb: (u8, u8, u8)) -> (f64, f64, f64) {
+    let (r, g, b) = rgb;
+    let (r, g, b) = (linearize(r), linearize(g), linearize(b));
+
+    // To XYZ, already divided by the white point
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+    let (fx, fy, fz) = (lab_f(x), lab_f(y), lab_f(z));
+    return (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz));
+}
+
+/** Undoes sRGB gamma, giving a linear intensity from 0 to 1. */
+fn linearize(channel: u8) -> f64 {
+    let c = channel as f64 / 255.0;
+    if c <= 0.04045 {
+        return c / 12.92;
+    }
+    return ((c + 0.055) / 1.055).powf(2.4);
+}
+
+/** The nonlinear part of the XYZ to L*a*b* conversion. */
+fn lab_f(t: f64) -> f64 {
+    if t > 216.0 / 24389.0 {
+        return t.cbrt();
+    }
+    return (24389.0 / 27.0 * t + 16.0) / 116.0;
+}
+
+/** Returns the RGB value of a palette color, as xterm has it by default.  The
+ * first sixteen are anyone's guess, since users love to change them. */
+pub fn index_to_rgb(n: u8) -> (u8, u8, u8) {
+    static BASIC: [(u8, u8, u8); 16] = [
+        (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0),
+        (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+        (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0),
+        (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+    ];
+
+    if n < 16 {
+        return BASIC[n as usize];
+    }
+    else if n < 232 {
+        // 6x6x6 color cube
+        let i = n - 16;
+        return (cube_level(i / 36), cube_level(i / 6 % 6), cube_level(i % 6));
+    }
+    else {
+        // Grayscale ramp, not including black or white
+        let gray = 8 + 10 * (n - 232);
+        return (gray, gray, gray);
+    }
+}
+
+/** Converts one coordinate of the color cube, 0 through 5, to a channel value.
+ * The steps aren't even: black is 0, but the rest start at 95. */
+fn cube_level(i: u8) -> u8 {
+    if i == 0 {
+        return 0;
+    }
+    return 55 + 40 * i;
+}
+
+/** How many answers a ColorMapper remembers.  A program drawing gradients in
+ * truecolor could ask about millions of colors, so past this, the cache
+ * starts over. */
+static CACHE_LIMIT: usize = 4096;
+
+/** Maps colors onto the ones a particular terminal can actually show, and
+ * remembers the answers. */
+pub struct ColorMapper {
+    /** The terminal's `colors` capability; zero for monochrome. */
+    colors: usize,
+    truecolor: bool,
+    metric: DistanceMetric,
+    cache: HashMap<Color, Color>,
+}
+
+impl ColorMapper {
+    pub fn new(colors: usize, truecolor: bool) -> ColorMapper {
+        return ColorMapper{
+            colors: colors,
+            truecolor: truecolor,
+            metric: DistanceMetric::Redmean,
+            cache: HashMap::new(),
+        };
+    }
+
+    pub fn metric(&self) -> DistanceMetric {
+        return self.metric;
+    }
+
+    pub fn set_metric(&mut self, metric: DistanceMetric) {
+        if metric != self.metric {
+            self.metric = metric;
+            self.cache.clear();
+        }
+    }
+
+    /** Returns the closest color to `color` that the terminal can show.  On a
+     * monochrome terminal, that's always the default. */
+    pub fn map(&mut self, color: Color) -> Color {
+        match self.cache.get(&color) {
+            Some(&mapped) => return mapped,
+            None => (),
+        }
+
+        let mapped = self.compute(color);
+        if self.cache.len() >= CACHE_LIMIT {
+            self.cache.clear();
+        }
+        self.cache.insert(color, mapped);
+        return mapped;
+    }
+
+    fn compute(&self, color: Color) -> Color {
+        let rgb = match color {
+            Color::Default => return color,
+            Color::Rgb(r, g, b) => {
+                if self.truecolor {
+                    return color;
+                }
+                (r, g, b)
+            }
+            _ => {
+                let n = color.index().unwrap();
+                if (n as usize) < self.colors {
+                    return color;
+                }
+                index_to_rgb(n)
+            }
+        };
+
+        // Only consider colors whose RGB values we can trust.  With the full
+        // 256, the first sixteen are skipped, since they're so often
+        // customized; the cube and ramp cover the same ground anyway.
+        let candidates = if self.colors >= 256 {
+            range(16, 256)
+        }
+        else if self.colors >= 16 {
+            range(0, 16)
+        }
+        else if self.colors >= 8 {
+            range(0, 8)
+        }
+        else {
+            return Color::Default;
+        };
+
+        let mut best = 0;
+        let mut best_distance: f64 = Float::infinity();
+        for n in candidates {
+            let distance = self.metric.distance(rgb, index_to_rgb(n as u8));
+            if distance < best_distance {
+                best = n;
+                best_distance = distance;
+            }
+        }
+        return Color::Indexed(best as u8);
+    }
+}
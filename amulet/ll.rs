@@ -1,39 +1,187 @@
-/** Low-level ncurses wrapper, for simple or heavily customized applications. */
-
-use libc::{c_char,c_int,c_long,c_short};
-use std::ffi::CString;
-use std::ffi::c_str_to_bytes;
-use std::ptr;
-use std::str;
-use std::str::from_c_str;
+/** Low-level terminal wrapper, for simple or heavily customized applications. */
+
 use libc;
+use libc::c_int;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 use std::io;
-use std::mem::transmute;
+use std::io::{IoError,IoResult};
+use std::cmp::{max,min};
+use std::os;
 use std::vec;
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Cell,RefCell};
+use std::sync::atomic::{AtomicUsize,ATOMIC_USIZE_INIT,Ordering};
 
-use c;
+use color::{Color,ColorDepth,ColorMapper,DistanceMetric,index_to_rgb};
+use terminfo::{CapabilityType,TermInfo,TermInfoError};
 use termios;
+use tparm;
+use tparm::{Padding,Param};
 use trie::Trie;
 
-extern {
-    fn setlocale(category: c_int, locale: *mut c_char) -> *mut c_char;
-
-    // XXX why the fuck is this not available
-    static stdout: *mut libc::FILE;
-}
 
+/** One of the capability methods from caps.rs, like
+ * `TerminalInfo::enter_ca_mode`. */
+pub type CapMethod<'a> = fn(&TerminalInfo<'a>) -> Option<()>;
 
 /** Prints a given termcap sequence when it goes out of scope. */
 pub struct TidyTermcap<'a> {
     terminfo: &'a TerminalInfo<'a>,
-    cap: &'static str,
+    undo: CapMethod<'a>,
 }
 #[unsafe_destructor]
 impl<'a> Drop for TidyTermcap<'a> {
     fn drop(&mut self) {
-        self.terminfo.write_cap(self.cap);
+        (self.undo)(self.terminfo);
+        self.terminfo.flush();
+    }
+}
+
+
+/** Everything that can go wrong while setting up a terminal.  If you get one
+ * of these, you probably want to fall back to plain line-based output. */
+#[derive(Show)]
+pub enum SetupError {
+    /** There's no terminfo database anywhere we know to look. */
+    NoDatabase,
+    /** The terminfo database has never heard of this terminal.  The name is
+     * empty if TERM isn't set at all. */
+    UnknownTerminal(String),
+    /** The terminfo entry exists, but is unreadable or corrupt. */
+    BadTerminfo(TermInfoError),
+    /** The terminal is hard-copy, i.e. it prints on paper. */
+    Hardcopy,
+    /** The given fd isn't a terminal at all; e.g., output is piped. */
+    NotATty(c_int),
+    /** The tty wouldn't give up its termios settings. */
+    Termios(IoError),
+    /** Something else went wrong with an fd, e.g. we couldn't open a stream
+     * for it. */
+    Io(IoError),
+}
+
+impl fmt::String for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match *self {
+            SetupError::NoDatabase => write!(f, "couldn't find terminfo database"),
+            SetupError::UnknownTerminal(ref name) if name.is_empty() => write!(f, "TERM isn't set"),
+            SetupError::UnknownTerminal(ref name) => write!(f, "unknown terminal type {}", name),
+            SetupError::BadTerminfo(ref err) => write!(f, "couldn't read terminfo entry: {:?}", err),
+            // The manual puts this as "terminal is hard-copy" but come on.
+            SetupError::Hardcopy => write!(f, "terminal appears to be made of paper"),
+            SetupError::NotATty(fd) => write!(f, "fd {} is not a terminal", fd),
+            SetupError::Termios(ref err) => write!(f, "couldn't get terminal settings: {}", err),
+            SetupError::Io(ref err) => write!(f, "couldn't open terminal: {}", err),
+        };
+    }
+}
+
+impl Error for SetupError {
+    fn description(&self) -> &str {
+        return match *self {
+            SetupError::NoDatabase => "no terminfo database",
+            SetupError::UnknownTerminal(_) => "unknown terminal type",
+            SetupError::BadTerminfo(_) => "bad terminfo entry",
+            SetupError::Hardcopy => "hard-copy terminal",
+            SetupError::NotATty(_) => "not a terminal",
+            SetupError::Termios(_) => "termios failure",
+            SetupError::Io(_) => "I/O error",
+        };
+    }
+}
+
+
+/** Figures out the terminal size, as (rows, columns).  Asks the kernel first,
+ * then tries $LINES and $COLUMNS, then terminfo, and finally just guesses. */
+fn query_size(fd: c_int, db: &TermInfo) -> (usize, usize) {
+    // TODO rather not dip into `imp`, but `pub use` isn't working right
+    match termios::imp::request_terminal_size(fd) {
+        Some(size) => return size,
+        None => (),
+    }
+
+    let rows = env_number("LINES")
+        .or_else(|| db.number("lines").map(|n| n as usize))
+        .unwrap_or(24);
+    let cols = env_number("COLUMNS")
+        .or_else(|| db.number("cols").map(|n| n as usize))
+        .unwrap_or(80);
+    return (rows, cols);
+}
+
+fn env_number(var: &str) -> Option<usize> {
+    return os::getenv(var).and_then(|value| value.as_slice().parse::<usize>());
+}
+
+/** Opens a stream on a copy of the given fd, so the caller keeps ownership of
+ * the original. */
+fn open_fd_stream(fd: c_int) -> Result<io::PipeStream, SetupError> {
+    let new_fd = unsafe { libc::dup(fd) };
+    if new_fd < 0 {
+        return Err(SetupError::Io(IoError::last_error()));
+    }
+    return io::PipeStream::open(new_fd).map_err(|err| SetupError::Io(err));
+}
+
+
+/** A string capability, parsed once when the terminal is set up. */
+enum CachedCap {
+    /** Takes no arguments, so it's already been formatted. */
+    Constant(Vec<u8>),
+    /** Needs formatting every time it's used. */
+    Program(tparm::Program),
+}
+
+
+/** Optional features a terminal may advertise.  Most of these come from
+ * extended capabilities, which are only conventions, so a terminal that
+ * doesn't mention one might well support it anyway -- but we can't know. */
+#[derive(Clone, Copy, Show)]
+pub struct Features {
+    /** 24-bit color, via `Tc` (tmux's convention) or `RGB` (ncurses'). */
+    pub truecolor: bool,
+    /** Curly, dotted, etc. underlines, via `Smulx`. */
+    pub styled_underline: bool,
+    /** Underlines in a different color from the text, via `Setulc`. */
+    pub underline_color: bool,
+}
+
+impl Features {
+    fn detect(db: &TermInfo) -> Features {
+        return Features{
+            // RGB can be a flag, a number, or a string, all meaning "yes"
+            truecolor: db.flag("Tc") == Some(true) || db.flag("RGB") == Some(true)
+                || db.number("RGB").is_some() || db.string("RGB").is_some(),
+            styled_underline: db.string("Smulx").is_some(),
+            underline_color: db.string("Setulc").is_some(),
+        };
+    }
+}
+
+
+/** Collects everything written to the terminal until it's explicitly flushed,
+ * so a whole frame can go out in a single write. */
+struct OutputBuffer<'a> {
+    out: Box<io::Writer + 'a>,
+    buffer: Vec<u8>,
+}
+
+impl<'a> Writer for OutputBuffer<'a> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        self.buffer.push_all(buf);
+        return Ok(());
+    }
+
+    /** Sends the buffer along.  Note that tputs flushes before it sleeps for
+     * padding, so delays still land in the right place. */
+    fn flush(&mut self) -> IoResult<()> {
+        if ! self.buffer.is_empty() {
+            try!(self.out.write(self.buffer.as_slice()));
+            self.buffer.clear();
+        }
+        return self.out.flush();
     }
 }
 
@@ -42,87 +190,174 @@ pub struct TerminalInfo<'a> {
     pub in_fd: c_int,
     pub in_file: RefCell<Box<io::Reader + 'a>>,
     pub out_fd: c_int,
-    out_file: RefCell<Box<io::Writer + 'a>>,
+    out_file: RefCell<OutputBuffer<'a>>,
 
     pub keypress_trie: Trie<u8, Key>,
+    pub features: Features,
 
-    c_terminfo: *mut c::TERMINAL,
+    db: TermInfo,
+    caps: HashMap<String, CachedCap>,
+    static_vars: RefCell<tparm::Variables>,
+    padding: Padding,
+    color_mapper: RefCell<ColorMapper>,
     tidy_termstate: termios::TidyTerminalState,
 
+    /** Current size, as (rows, columns). */
+    size: Cell<(usize, usize)>,
+    /** termios::resize_generation() as of the last time we checked the size. */
+    resize_generation: Cell<usize>,
+    /** termios::resume_generation() as of the last time we checked. */
+    resume_generation: Cell<usize>,
+    /** Where the cursor is, as (row, column), or None if we've lost track. */
+    cursor: Cell<Option<(usize, usize)>>,
+
     //term_type: &str,
 }
 
 #[unsafe_destructor]
 impl<'a> Drop for TerminalInfo<'a> {
     fn drop(&mut self) {
+        self.flush();
         self.tidy_termstate.restore();
     }
 }
 impl<'a> TerminalInfo<'a> {
-    #[fixed_stack_segment]
-    pub fn new() -> TerminalInfo<'a> {
-        let mut error_code: c_int = 0;
-        // NULL first arg means read TERM from env (TODO).
-        // second arg is a fd to spew to on error, but it's not used when there's
-        // an error pointer.
-        // third arg is a var to stick the error code in.
-        // TODO allegedly setupterm doesn't work on BSD?
-        unsafe {
-            let res = c::setupterm(ptr::null(), -1, &mut error_code);
-
-            if res != c::OK {
-                if error_code == -1 {
-                    panic!("Couldn't find terminfo database");
-                }
-                else if error_code == 0 {
-                    panic!("Couldn't identify terminal");
-                }
-                else if error_code == 1 {
-                    // The manual puts this as "terminal is hard-copy" but come on.
-                    panic!("Terminal appears to be made of paper");
-                }
-                else {
-                    panic!("Something is totally fucked");
-                }
+    /** Sets up the terminal on stdin and stdout, with its type taken from
+     * TERM. */
+    pub fn new() -> Result<TerminalInfo<'a>, SetupError> {
+        return TerminalInfo::open(None, 0, 1);
+    }
+
+    /** Sets up a terminal on arbitrary fds.  If `term_name` is None, it's
+     * taken from TERM.
+     *
+     * The fds are duplicated for reading and writing, so closing them is
+     * still your job, but not until this object is gone.
+     */
+    pub fn open(term_name: Option<&str>, in_fd: c_int, out_fd: c_int) -> Result<TerminalInfo<'a>, SetupError> {
+        for &fd in [in_fd, out_fd].iter() {
+            if ! termios::is_tty(fd) {
+                return Err(SetupError::NotATty(fd));
             }
         }
 
-        // Okay; now terminfo is sitting in a magical global somewhere.  Snag a
-        // pointer to it.
-        let terminfo = c::cur_term;
+        let term_name = match term_name {
+            Some(term_name) => term_name.to_string(),
+            None => match os::getenv("TERM") {
+                Some(term_name) => term_name,
+                None => return Err(SetupError::UnknownTerminal(String::new())),
+            },
+        };
+
+        let db = match TermInfo::load(term_name.as_slice()) {
+            Ok(db) => db,
+            Err(TermInfoError::NoDatabase) => return Err(SetupError::NoDatabase),
+            Err(TermInfoError::NotFound(name)) => return Err(SetupError::UnknownTerminal(name)),
+            Err(err) => return Err(SetupError::BadTerminfo(err)),
+        };
+
+        if db.flag("hc") == Some(true) {
+            return Err(SetupError::Hardcopy);
+        }
+
+        let mut tidy_termstate = match termios::TidyTerminalState(in_fd) {
+            Ok(tidy_termstate) => tidy_termstate,
+            Err(err) => return Err(SetupError::Termios(err)),
+        };
 
+        // Input always gets its own unbuffered stream, even for stdin: we poll
+        // the fd to notice resizes, and that's a lie if there are keypresses
+        // sitting in a buffer somewhere.  Output uses stdout when it can,
+        // since that's shared with the rest of the process
+        let in_file = Box::new(try!(open_fd_stream(in_fd))) as Box<io::Reader>;
+        let out_file = if out_fd == 1 {
+            Box::new(io::stdout()) as Box<io::Writer>
+        }
+        else {
+            Box::new(try!(open_fd_stream(out_fd))) as Box<io::Writer>
+        };
+
+        let padding = Padding{
+            baud_rate: termios::output_baud_rate(out_fd),
+            padding_baud_rate: db.number("pb").unwrap_or(0) as usize,
+            pad_char: if db.flag("npc") == Some(true) { None } else {
+                Some(db.string("pad").and_then(|pad| pad.first()).map(|&b| b).unwrap_or(0))
+            },
+            xon: db.flag("xon") == Some(true),
+        };
+
+        // Extended key capabilities, like kUP5 for modified arrows, don't have
+        // a Key to become yet, so leave them out instead of turning them all
+        // into UNKNOWN
+        let extended_names = db.extended_names();
         let mut keypress_trie = Trie::new();
-        let mut p: *const *const c_char = &c::strnames[0];
-        unsafe {
-            while *p != ptr::null() {
-                let capname = from_c_str(*p);
-
-                if capname.char_at(0) == 'k' {
-                    let cap = c::tigetstr(*p);
-                    if cap != ptr::null() {
-                        let cap_key = c_str_to_bytes(&cap);
-                        keypress_trie.insert(cap_key, cap_to_key(capname));
-                    }
-                }
+        for capname in db.string_names().iter() {
+            if capname.char_at(0) == 'k' && ! extended_names.contains(capname) {
+                let cap_key = db.string(*capname).unwrap();
+                keypress_trie.insert(cap_key, cap_to_key(*capname));
+            }
+        }
 
-                p = p.offset(1);
+        // Parse every string capability up front, so writing one later is
+        // just a lookup.  Anything that doesn't parse is treated as missing
+        let mut static_vars = tparm::Variables::new();
+        let mut caps = HashMap::new();
+        for capname in db.string_names().iter() {
+            let program = match tparm::Program::compile(db.string(*capname).unwrap()) {
+                Ok(program) => program,
+                Err(_) => continue,
+            };
+            let cached = if program.is_constant() {
+                match program.expand(&[], &mut static_vars) {
+                    Ok(formatted) => CachedCap::Constant(formatted),
+                    Err(_) => continue,
+                }
             }
+            else {
+                CachedCap::Program(program)
+            };
+            caps.insert(capname.to_string(), cached);
         }
 
-        return TerminalInfo{
-            // TODO would be nice to parametrize these, but Reader and Writer do
-            // not yet expose a way to get the underlying fd, which makes the API
-            // sucky
-            in_fd: 0,
-            in_file: RefCell::new(Box::new(io::stdin()) as Box<io::Reader>),
-            out_fd: 1,
-            out_file: RefCell::new(Box::new(io::stdout()) as Box<io::Writer>),
+        // Ask for the generation before the size, so a resize in between
+        // isn't missed
+        tidy_termstate.install_resize_handler();
+        let resize_generation = termios::resize_generation();
+        let size = query_size(out_fd, &db);
+        tidy_termstate.install_resume_handler();
+        let resume_generation = termios::resume_generation();
+
+        let features = Features::detect(&db);
+        let colors = db.number("colors").unwrap_or(0) as usize;
+
+        return Ok(TerminalInfo{
+            in_fd: in_fd,
+            in_file: RefCell::new(in_file),
+            out_fd: out_fd,
+            out_file: RefCell::new(OutputBuffer{ out: out_file, buffer: vec![] }),
 
             keypress_trie: keypress_trie,
+            features: features,
+
+            db: db,
+            caps: caps,
+            static_vars: RefCell::new(static_vars),
+            padding: padding,
+            color_mapper: RefCell::new(ColorMapper::new(colors, features.truecolor)),
+            tidy_termstate: tidy_termstate,
+
+            size: Cell::new(size),
+            resize_generation: Cell::new(resize_generation),
+            resume_generation: Cell::new(resume_generation),
+            cursor: Cell::new(None),
+        });
+    }
 
-            c_terminfo: terminfo,
-            tidy_termstate: termios::TidyTerminalState(0),
-        };
+
+    /** Returns a new termios state object that will put the terminal back
+     * the way it is right now. */
+    pub fn checkpoint_termstate(&self) -> termios::TidyTerminalState {
+        return self.tidy_termstate.checkpoint();
     }
 
 
@@ -130,286 +365,1057 @@ impl<'a> TerminalInfo<'a> {
     // Capability inspection
     // TODO ideally, ultimately, every useful cap will be covered here...
 
-    // TODO these should:
-    // - only do the ioctl once and cache it
-    // - handle failure in ioctl
-    // - handle TIOCGSIZE instead of that other thing
-    // - handle SIGWINCH
-    // - fall back to environment
-    // - THEN fall back to termcap
+    // Size is cached, and only updated by check_resize(), so it won't change
+    // out from under you halfway through drawing something.
+    // TODO handle TIOCGSIZE for the BSDs
     pub fn height(&self) -> usize {
-        // TODO rather not dip into `imp`, but `pub use` isn't working right
-        let (_, height) = termios::imp::request_terminal_size(self.out_fd);
+        let (height, _) = self.size.get();
         return height;
-        //return self.numeric_cap("lines");
     }
     pub fn width(&self) -> usize {
-        let (width, _) = termios::imp::request_terminal_size(self.out_fd);
+        let (_, width) = self.size.get();
         return width;
-        //return self.numeric_cap("cols");
+    }
+
+    /** Checks whether the terminal has been resized since the last check.
+     * If so, updates the cached size and returns it as (rows, columns). */
+    pub fn check_resize(&self) -> Option<(usize, usize)> {
+        let generation = termios::resize_generation();
+        if generation == self.resize_generation.get() {
+            return None;
+        }
+        self.resize_generation.set(generation);
+
+        let size = query_size(self.out_fd, &self.db);
+        self.size.set(size);
+        // Some terminals rewrap on resize, which moves the cursor
+        self.cursor.set(None);
+        return Some(size);
+    }
+
+    /** Checks whether the process has been stopped and resumed (e.g. with ^Z
+     * and `fg`) since the last check, in which case the screen needs
+     * redrawing from scratch. */
+    pub fn check_resumed(&self) -> bool {
+        let generation = termios::resume_generation();
+        if generation == self.resume_generation.get() {
+            return false;
+        }
+        self.resume_generation.set(generation);
+        // Whatever ran in the meantime could have left the cursor anywhere
+        self.cursor.set(None);
+        return true;
     }
 
     // ------------------------------------------------------------------------
     // Very-low-level capability inspection
+    // These all return None both when the terminal lacks a capability and
+    // when the capability is a different type; use capability_type() to tell
+    // the difference.
+
+    /** Returns what kind of capability `name` is, or None if terminfo has
+     * never heard of it. */
+    pub fn capability_type(&self, name: &str) -> Option<CapabilityType> {
+        return self.db.capability_type(name);
+    }
+
+    /** Returns a boolean capability.  Flags the terminal doesn't have are
+     * false, not None. */
+    pub fn flag_cap(&self, name: &str) -> Option<bool> {
+        return self.db.flag(name);
+    }
+
+    pub fn numeric_cap(&self, name: &str) -> Option<u32> {
+        return self.db.number(name);
+    }
+
+    /** Returns a string capability, unformatted. */
+    pub fn string_cap(&self, name: &str) -> Option<String> {
+        return self.db.string(name).map(|value| String::from_utf8_lossy(value).into_owned());
+    }
 
-    #[fixed_stack_segment]
-    fn flag_cap(&self, name: &str) -> bool {
-        unsafe {
-            c::set_curterm(self.c_terminfo);
+    /** Returns whether the terminal has a particular string capability.
+     * Capabilities too broken to parse don't count. */
+    pub fn has_cap(&self, name: &str) -> bool {
+        return self.caps.contains_key(name);
+    }
 
-            let mut value = 0;
-            let c_name = CString::from_slice(name.as_bytes());
-            value = c::tigetflag(c_name.as_ptr());
+    // NOTE: most of the time you want the per-capability methods generated in
+    // caps.rs, which check the number of arguments at compile time.  These are
+    // for the cases where the capability name isn't known until runtime.
 
-            if value == -1 {
-                // wrong type
-                panic!("wrong type");
+    /** Returns a string capability, formatted with the passed arguments.
+     *
+     * Passing the correct number of arguments is your problem, though any
+     * missing arguments become zero.  No capability requires more than 9
+     * arguments.  Padding is left as-is; `write_formatted()` will take care
+     * of it.
+     *
+     * Returns None if the terminal doesn't have the capability, or if the
+     * arguments don't make sense for it.
+     */
+    pub fn format_cap(&self, name: &str, params: &[Param]) -> Option<Vec<u8>> {
+        return match self.caps.get(name) {
+            Some(&CachedCap::Constant(ref formatted)) => Some(formatted.clone()),
+            Some(&CachedCap::Program(ref program)) => {
+                let mut static_vars = self.static_vars.borrow_mut();
+                program.expand(params, &mut *static_vars).ok()
             }
+            None => None,
+        };
+    }
 
-            // Otherwise, is 0 or 1
-            return value != 0;
-        }
+    /** Returns how many bytes a capability would take to send, formatted
+     * with the given arguments, or None if the terminal doesn't have it.
+     * Handy for picking the cheapest of several ways to do something. */
+    pub fn cap_length(&self, name: &str, params: &[Param]) -> Option<usize> {
+        return match self.caps.get(name) {
+            Some(&CachedCap::Constant(ref formatted)) => Some(formatted.len()),
+            Some(_) => self.format_cap(name, params).map(|formatted| formatted.len()),
+            None => None,
+        };
     }
 
-    #[fixed_stack_segment]
-    fn numeric_cap(&self, name: &str) -> u32 {
-        unsafe {
-            c::set_curterm(self.c_terminfo);
+    /** Writes a capability formatted with any number of arguments.  Returns
+     * None, and writes nothing, if the terminal doesn't have it. */
+    pub fn write_capv(&self, name: &str, params: &[Param]) -> Option<()> {
+        // Constant capabilities go straight out, without even a copy
+        let formatted;
+        let bytes = match self.caps.get(name) {
+            Some(&CachedCap::Constant(ref formatted)) => formatted.as_slice(),
+            Some(_) => {
+                formatted = match self.format_cap(name, params) {
+                    Some(formatted) => formatted,
+                    None => return None,
+                };
+                formatted.as_slice()
+            }
+            None => return None,
+        };
+
+        let mut out_file = self.out_file.borrow_mut();
+        // TODO the number of affected lines only matters for a handful of
+        // capabilities, on terminals nobody has used since 1985
+        tparm::tputs(&mut *out_file, bytes, 1, &self.padding);
+        self.cursor.set(cursor_after_cap(name, self.cursor.get()));
+        return Some(());
+    }
 
-            let mut value = -1;
-            let c_name = CString::from_slice(name.as_bytes());
-            value = c::tigetnum(c_name.as_ptr());
+    /** Writes an already-formatted capability, or several strung together,
+     * honoring any padding.  These are assumed not to move the cursor, as is
+     * the case for attribute changes; if that's not so, call
+     * `forget_cursor_position()` afterwards. */
+    pub fn write_formatted(&self, bytes: &[u8]) {
+        let mut out_file = self.out_file.borrow_mut();
+        tparm::tputs(&mut *out_file, bytes, 1, &self.padding);
+    }
+
+    /** Writes a capability to the terminal.  Returns None, and writes
+     * nothing, if the terminal doesn't have it. */
+    pub fn write_cap(&self, cap_name: &str) -> Option<()> {
+        return self.write_capv(cap_name, &[]);
+    }
+    pub fn write_cap1(&self, cap_name: &str, arg1: isize) -> Option<()> {
+        return self.write_capv(cap_name, &[Param::Number(arg1)]);
+    }
+    pub fn write_cap2(&self, cap_name: &str, arg1: isize, arg2: isize) -> Option<()> {
+        return self.write_capv(cap_name, &[Param::Number(arg1), Param::Number(arg2)]);
+    }
+
+    /** Writes a capability now, and another one to undo it when the
+     * returned guard goes out of scope, e.g.
+     * `write_tidy_cap(TerminalInfo::enter_ca_mode, TerminalInfo::exit_ca_mode)`. */
+    pub fn write_tidy_cap(&'a self, do_cap: CapMethod<'a>, undo_cap: CapMethod<'a>) -> TidyTermcap<'a> {
+        do_cap(self);
+
+        return TidyTermcap{ terminfo: self, undo: undo_cap };
+    }
+
+    // Output
+    // Nothing actually reaches the terminal until flush() is called.
+
+    pub fn write(&self, s: &str) {
+        // TODO well.  should be a bit more flexible, i guess.
+        self.out_file.borrow_mut().write_str(s);
+        self.cursor.set(self.cursor_after_text(s));
+    }
 
-            if value == -2 {
-                // wrong type
-                panic!("wrong type");
+    /** Works out where writing `s` leaves the cursor.  Anything that isn't
+     * plain text is assumed to send it somewhere unknown. */
+    fn cursor_after_text(&self, s: &str) -> Option<(usize, usize)> {
+        let (row, mut col) = match self.cursor.get() {
+            Some(position) => position,
+            None => return None,
+        };
+        for cluster in grapheme_clusters(s).iter() {
+            // Terminals don't agree on how wide joined emoji are
+            if cluster.chars().any(|ch| ch == ZERO_WIDTH_JOINER) {
+                return None;
             }
-            else if value == -1 {
-                // missing; should be None
-                panic!("missing; should be None");
+            match cluster_width(*cluster) {
+                Some(width) => col += width,
+                // Control characters
+                None => return None,
             }
+        }
+        // Writing in the last column either wraps or doesn't, depending on
+        // the terminal (and, with xenl, on what comes next), so don't guess
+        if col >= self.width() {
+            return None;
+        }
+        return Some((row, col));
+    }
+
+    /** Sends everything written so far to the terminal.  Returns the number
+     * of bytes sent, which is handy for seeing how much a frame costs. */
+    pub fn flush(&self) -> IoResult<usize> {
+        let mut out_file = self.out_file.borrow_mut();
+        let pending = out_file.buffer.len();
+        try!(out_file.flush());
+        return Ok(pending);
+    }
+
+
 
-            return value as u32;
+    // Some stuff
+
+    /** Changes the text color.  Colors the terminal doesn't have are
+     * replaced with the closest one it does.  Returns None if the terminal
+     * can't do color at all. */
+    pub fn set_fg_color(&self, color: Color) -> Option<()> {
+        return self.set_color(color, false);
+    }
+
+    /** Changes the background color, as with `set_fg_color()`. */
+    pub fn set_bg_color(&self, color: Color) -> Option<()> {
+        return self.set_color(color, true);
+    }
+
+    /** Returns roughly how many colors the terminal can show. */
+    pub fn color_depth(&self) -> ColorDepth {
+        let colors = self.numeric_cap("colors").unwrap_or(0);
+        if self.features.truecolor || colors >= 0x1000000 {
+            return ColorDepth::Truecolor;
+        }
+        else if colors >= 256 {
+            return ColorDepth::Colors256;
+        }
+        else if colors >= 8 {
+            return ColorDepth::Colors16;
         }
+        return ColorDepth::Monochrome;
     }
 
-    #[fixed_stack_segment]
-    fn _string_cap_cstr(&self, name: &str) -> *const c_char {
-        unsafe {
-            c::set_curterm(self.c_terminfo);
+    /** Returns how colors the terminal can't show are matched to ones it
+     * can. */
+    pub fn distance_metric(&self) -> DistanceMetric {
+        return self.color_mapper.borrow().metric();
+    }
 
-            let c_name = CString::from_slice(name.as_bytes());
-            let value = c::tigetstr(c_name.as_ptr());
+    pub fn set_distance_metric(&self, metric: DistanceMetric) {
+        self.color_mapper.borrow_mut().set_metric(metric);
+    }
 
-            if value == ptr::null() {
-                // missing; should be None really
-                panic!("missing; should be None really");
+    fn set_color(&self, color: Color, background: bool) -> Option<()> {
+        return self.color_sequence(color, background).map(|sequence| {
+            self.write_formatted(sequence.as_slice());
+        });
+    }
+
+    /** Returns what to send to change the text or background color, or None
+     * if the terminal can't do color.  Note that resetting to the default
+     * color resets both colors on terminals without `setaf`. */
+    pub fn color_sequence(&self, color: Color, background: bool) -> Option<Vec<u8>> {
+        // Swap in the closest color the terminal can actually do
+        let color = self.color_mapper.borrow_mut().map(color);
+
+        let ansi_cap = if background { "setab" } else { "setaf" };
+        return match color {
+            Color::Default => {
+                // terminfo can only reset both colors at once, with op.  But
+                // anything with ANSI colors understands SGR 39 and 49
+                if self.has_cap(ansi_cap) {
+                    let sgr: &[u8] = if background { b"\x1b[49m" } else { b"\x1b[39m" };
+                    Some(sgr.to_vec())
+                }
+                else {
+                    self.format_cap("op", &[])
+                }
             }
-            else if transmute::<_, isize>(value) == -1 {
-                // wrong type
-                panic!("wrong type");
+            Color::Rgb(r, g, b) => {
+                if ! self.features.truecolor {
+                    return None;
+                }
+                // Terminals using ncurses' RGB convention claim 2**24 colors
+                // and take the color itself as the argument to setaf
+                if self.numeric_cap("colors").unwrap_or(0) >= 0x1000000 {
+                    let packed = ((r as isize) << 16) | ((g as isize) << 8) | (b as isize);
+                    return self.format_cap(ansi_cap, &[Param::Number(packed)]);
+                }
+                let sgr = format!("\x1b[{};2;{};{};{}m", if background { 48 } else { 38 }, r, g, b);
+                Some(sgr.into_bytes())
             }
+            _ => {
+                let index = color.index().unwrap();
+                // Direct-color terminals only keep the first eight colors as
+                // a palette; anything bigger is taken as RGB, so convert
+                if index >= 8 && self.numeric_cap("colors").unwrap_or(0) >= 0x1000000 {
+                    let (r, g, b) = index_to_rgb(index);
+                    let packed = ((r as isize) << 16) | ((g as isize) << 8) | (b as isize);
+                    return self.format_cap(ansi_cap, &[Param::Number(packed)]);
+                }
 
-            return value;
+                let n = index as isize;
+                match self.format_cap(ansi_cap, &[Param::Number(n)]) {
+                    Some(sequence) => return Some(sequence),
+                    None => (),
+                }
+                // setf and setb are the pre-ANSI versions, which number colors
+                // differently: red and blue are swapped
+                let bgr = (n & !5) | ((n & 1) << 2) | ((n & 4) >> 2);
+                self.format_cap(if background { "setb" } else { "setf" }, &[Param::Number(bgr)])
+            }
+        };
+    }
+
+    /** Turns on every attribute in `style` that isn't already on in
+     * `current`.  There's no way to turn attributes off individually, so
+     * this never does; that takes `exit_attribute_mode()`.  Attributes the
+     * terminal can't do are silently skipped.  Colors are left alone. */
+    pub fn enter_attributes(&self, style: &Style, current: &Style) {
+        if style.is_bold && ! current.is_bold {
+            self.enter_bold_mode();
+        }
+        if style.is_underline && ! current.is_underline {
+            self.enter_underline_mode();
+        }
+        if style.is_italic && ! current.is_italic {
+            self.enter_italics_mode();
+        }
+        if style.is_reverse && ! current.is_reverse {
+            self.enter_reverse_mode();
+        }
+        if style.is_dim && ! current.is_dim {
+            self.enter_dim_mode();
+        }
+        if style.is_blink && ! current.is_blink {
+            self.enter_blink_mode();
+        }
+        if style.is_strikethrough && ! current.is_strikethrough {
+            self.enter_strikethrough_mode();
+        }
+        if style.is_invisible && ! current.is_invisible {
+            self.enter_secure_mode();
+        }
+        if style.is_overline && ! current.is_overline {
+            self.enter_overline_mode();
         }
     }
 
-    fn string_cap(&self, name: &str) -> &str {
-        let value = self._string_cap_cstr(name);
+    /** Moves the cursor to an absolute position.
+     *
+     * Uses `cup` if possible, but will make do with whatever the terminal
+     * has.  Returns None if the terminal can't get the cursor there, which
+     * is the case for e.g. `dumb`.
+     */
+    pub fn reposition(&self, x: usize, y: usize) -> Option<()> {
+        if self.cursor_address(y as isize, x as isize).is_some() {
+            self.cursor.set(Some((y, x)));
+            return Some(());
+        }
+
+        // Some terminals can only move along one axis at a time
+        if self.has_cap("vpa") && self.has_cap("hpa") {
+            if self.row_address(y as isize).is_none()
+                || self.column_address(x as isize).is_none()
+            {
+                self.forget_cursor_position();
+                return None;
+            }
+            self.cursor.set(Some((y, x)));
+            return Some(());
+        }
 
-        unsafe {
-            return from_c_str(value);
+        // Otherwise, go to the top left and walk from there
+        if self.cursor_home().is_none() {
+            self.forget_cursor_position();
+            return None;
         }
+        if self.move_relative("cud", "cud1", y).is_none()
+            || self.move_relative("cuf", "cuf1", x).is_none()
+        {
+            // Got partway, but there's no telling where
+            self.forget_cursor_position();
+            return None;
+        }
+        self.cursor.set(Some((y, x)));
+        return Some(());
+    }
+
+    /** Returns where the cursor is, as (row, column), if known.  It's known
+     * after moving it with `reposition()` or `move_cursor()`, and kept track
+     * of as text and capabilities are written, until something happens that
+     * could have sent it who knows where. */
+    pub fn cursor_position(&self) -> Option<(usize, usize)> {
+        return self.cursor.get();
     }
 
-    // TODO i am not really liking the string capability handling anywhere in
-    // here.
-    // the variable arguments thing sucks ass.  we should at LEAST check for
-    // how many arguments are expected, and ideally enforce it at compile time
-    // somehow -- perhaps with a method for every string cap.  (yikes.  having
-    // keys be a separate thing would help, though.)
+    /** Forgets where the cursor is, so the next move doesn't rely on it.
+     * Call this after writing anything that moves the cursor behind our
+     * back. */
+    pub fn forget_cursor_position(&self) {
+        self.cursor.set(None);
+    }
 
-    /** Returns a string capability, formatted with the passed vector of
-     * arguments.
+    /** Moves the cursor to the given row and column, as cheaply as possible.
      *
-     * Passing the correct number of arguments is your problem, though any
-     * missing arguments become zero.  No capability requires more than 9
-     * arguments.
+     * If the cursor's position is known, this might mean doing nothing at
+     * all, a carriage return, a relative move, `hpa`, or `ind`, rather than
+     * a full `cup`.  `reprint`, if given, is whatever's on the screen between
+     * the cursor and the destination, which has to be on the same row; if
+     * writing it out again with the current attributes would change nothing,
+     * that can be cheapest of all.
+     *
+     * Otherwise, or if the position isn't known, falls back to
+     * `reposition()`.
      */
-    #[fixed_stack_segment]
-    fn format_cap(&self, name: &str, args: Vec<isize>) -> &str {
-        unsafe {
-            c::set_curterm(self.c_terminfo);
+    pub fn move_cursor(&self, row: usize, col: usize, reprint: Option<&str>) -> Option<()> {
+        let (from_row, from_col) = match self.cursor.get() {
+            Some(position) => position,
+            None => return self.reposition(col, row),
+        };
+        if from_row == row && from_col == col {
+            return Some(());
+        }
 
-            let template = self._string_cap_cstr(name);
-            let padded_args = args + &[0, 0, 0, 0, 0, 0, 0, 0];
-            let formatted = c::tparm(
-                template,
-                padded_args[0] as c_long,
-                padded_args[1] as c_long,
-                padded_args[2] as c_long,
-                padded_args[3] as c_long,
-                padded_args[4] as c_long,
-                padded_args[5] as c_long,
-                padded_args[6] as c_long,
-                padded_args[7] as c_long,
-                padded_args[8] as c_long
-            );
+        let mut best = self.format_cap("cup", &[Param::Number(row as isize), Param::Number(col as isize)]);
+        let carriage_return = self.format_cap("cr", &[]);
+        for vertical in self.vertical_motions(from_row, row).into_iter() {
+            // A bare \n (i.e. cud1 or ind) only returns the cursor to the
+            // left edge if the tty turns it into \r\n.  termios::cbreak asks
+            // for that, but someone else may have set the tty up
+            let start_col = if is_newlines(vertical.as_slice()) && termios::translates_newlines(self.out_fd) {
+                0
+            }
+            else {
+                from_col
+            };
+            let mut candidates = vec![];
+
+            match self.horizontal_motion(start_col, col) {
+                Some(horizontal) => candidates.push(concat(&vertical, &horizontal)),
+                None => (),
+            }
+            if start_col != 0 {
+                match (&carriage_return, self.horizontal_motion(0, col)) {
+                    (&Some(ref cr), Some(horizontal)) => {
+                        candidates.push(concat(&concat(&vertical, cr), &horizontal));
+                    }
+                    _ => (),
+                }
+            }
 
-            return from_c_str(formatted);
+            for candidate in candidates.into_iter() {
+                let shorter = match best {
+                    Some(ref sequence) => candidate.len() < sequence.len(),
+                    None => true,
+                };
+                if shorter {
+                    best = Some(candidate);
+                }
+            }
         }
-    }
 
-    #[fixed_stack_segment]
-    fn _write_capx(&self, name: &str,
-            arg1: c_long, arg2: c_long, arg3: c_long,
-            arg4: c_long, arg5: c_long, arg6: c_long,
-            arg7: c_long, arg8: c_long, arg9: c_long)
-    {
-        unsafe {
-            c::set_curterm(self.c_terminfo);
+        // Rewriting what's already there is plain text, not a capability, so
+        // it has to be written separately
+        match reprint {
+            Some(text) if from_row == row && from_col + text_width(text) == col => {
+                let cheaper = match best {
+                    Some(ref sequence) => text.len() < sequence.len(),
+                    None => true,
+                };
+                if cheaper {
+                    self.write(text);
+                    return Some(());
+                }
+            }
+            _ => (),
+        }
 
-            let template = self._string_cap_cstr(name);
+        return match best {
+            Some(sequence) => {
+                self.write_formatted(sequence.as_slice());
+                self.cursor.set(Some((row, col)));
+                Some(())
+            }
+            None => self.reposition(col, row),
+        };
+    }
 
-            let formatted = c::tparm(
-                template, arg1, arg2, arg3, arg4, arg5, arg6, arg7, arg8, arg9);
+    /** Returns every reasonable way to move the cursor from one row to
+     * another without caring where it ends up horizontally.  `ind` only ever
+     * moves the cursor down here, never scrolls, since it's never used on
+     * the bottom row. */
+    fn vertical_motions(&self, from: usize, to: usize) -> Vec<Vec<u8>> {
+        let mut motions = vec![];
+        if from == to {
+            motions.push(vec![]);
+            return motions;
+        }
 
-            //unsafe { io::stderr().write_str(fmt!("%s\t%s\n", name, from_c_str(formatted))); }
+        match self.format_cap("vpa", &[Param::Number(to as isize)]) {
+            Some(sequence) => motions.push(sequence),
+            None => (),
+        }
+        let relative = if to > from {
+            match self.repeat_cap("ind", to - from) {
+                Some(sequence) => motions.push(sequence),
+                None => (),
+            }
+            self.relative_motion("cud", "cud1", to - from)
+        }
+        else {
+            self.relative_motion("cuu", "cuu1", from - to)
+        };
+        match relative {
+            Some(sequence) => motions.push(sequence),
+            None => (),
+        }
+        return motions;
+    }
 
-            // TODO we are supposed to use curses's tputs(3) to print formatted
-            // capabilities, because they sometimes contain "padding" of the
-            // form $<5>.  alas, tputs always prints to stdout!  but we don't
-            // yet allow passing in a custom fd so i guess that's okay.  would
-            // be nice to reimplement this in Rust code someday though.
-            c::putp(formatted);
+    /** Returns the cheapest way to move the cursor from one column to
+     * another on the same row. */
+    fn horizontal_motion(&self, from: usize, to: usize) -> Option<Vec<u8>> {
+        if from == to {
+            return Some(vec![]);
+        }
 
-            // TODO another reason dipping into C sucks: we have to manually
-            // flush its buffer every time we do so.
-            libc::fflush(stdout);
+        let absolute = self.format_cap("hpa", &[Param::Number(to as isize)]);
+        let relative = if to > from {
+            self.relative_motion("cuf", "cuf1", to - from)
         }
+        else {
+            self.relative_motion("cub", "cub1", from - to)
+        };
+        return shorter(absolute, relative);
     }
 
-    // TODO seems it would make sense to cache non-formatted capabilities (as
-    // well as numeric/flag ones), which i think blessings does
-    pub fn write_cap(&self, cap_name: &str) {
-        // If we're calling this function then this capability really shouldn't
-        // take any arguments, but someone might have screwed up, or it may
-        // have an escaped % or something.  Best do the whole formatting thing.
-        self._write_capx(cap_name, 0, 0, 0, 0, 0, 0, 0, 0, 0);
-    }
-    pub fn write_cap1(&self, cap_name: &str, arg1: isize) {
-        self._write_capx(cap_name, arg1 as c_long, 0, 0, 0, 0, 0, 0, 0, 0);
+    /** Returns the cheaper of the parameterized capability `cap_n` and
+     * `cap_1` repeated `n` times. */
+    fn relative_motion(&self, cap_n: &str, cap_1: &str, n: usize) -> Option<Vec<u8>> {
+        return shorter(
+            self.format_cap(cap_n, &[Param::Number(n as isize)]),
+            self.repeat_cap(cap_1, n));
     }
-    pub fn write_cap2(&self, cap_name: &str, arg1: isize, arg2: isize) {
-        self._write_capx(cap_name, arg1 as c_long, arg2 as c_long, 0, 0, 0, 0, 0, 0, 0);
+
+    fn repeat_cap(&self, name: &str, n: usize) -> Option<Vec<u8>> {
+        let once = match self.format_cap(name, &[]) {
+            Some(sequence) => sequence,
+            None => return None,
+        };
+        let mut out = vec![];
+        for _ in range(0, n) {
+            out.push_all(once.as_slice());
+        }
+        return Some(out);
     }
 
-    pub fn write_tidy_cap(&'a self, do_cap: &str, undo_cap: &'static str) -> TidyTermcap<'a> {
-        self.write_cap(do_cap);
+    /** Moves the cursor `n` cells in one direction, using either the
+     * parameterized capability or the single-step one repeatedly. */
+    fn move_relative(&self, cap_n: &str, cap_1: &str, n: usize) -> Option<()> {
+        if n == 0 {
+            return Some(());
+        }
+        if self.write_cap1(cap_n, n as isize).is_some() {
+            return Some(());
+        }
+        if ! self.has_cap(cap_1) {
+            return None;
+        }
+        for _ in range(0, n) {
+            self.write_cap(cap_1);
+        }
+        return Some(());
+    }
+}
 
-        return TidyTermcap{ terminfo: self, cap: undo_cap };
+// Capabilities that leave the cursor where it is.  Anything else that gets
+// written makes us lose track of it, unless it's handled in cursor_after_cap()
+static STATIONARY_CAPS: [&'static str; 32] = [
+    // Attributes
+    "sgr0", "sgr", "bold", "dim", "smul", "rmul", "Smulx", "Setulc", "sitm",
+    "ritm", "rev", "blink", "invis", "smso", "rmso", "smxx", "rmxx", "Smol",
+    "setaf", "setab", "setf", "setb", "op",
+    // Erasing
+    "el", "el1", "ed", "ech",
+    // Cursor appearance and keypad mode
+    "civis", "cnorm", "cvvis", "smkx", "rmkx",
+];
+
+/** Works out where writing the named capability leaves the cursor, given
+ * where it was before. */
+fn cursor_after_cap(name: &str, before: Option<(usize, usize)>) -> Option<(usize, usize)> {
+    if name == "clear" || name == "home" {
+        return Some((0, 0));
+    }
+    if name == "cr" {
+        return before.map(|(row, _)| (row, 0));
+    }
+    if STATIONARY_CAPS.iter().any(|&cap| cap == name) {
+        return before;
     }
+    return None;
+}
 
-    // TODO should capabilities just have a method apiece, like blessings?
+/** Returns whether `sequence` is nothing but one or more newlines. */
+fn is_newlines(sequence: &[u8]) -> bool {
+    return ! sequence.is_empty() && sequence.iter().all(|&b| b == b'\n');
+}
 
-    // Output
+fn concat(a: &Vec<u8>, b: &Vec<u8>) -> Vec<u8> {
+    let mut out = a.clone();
+    out.push_all(b.as_slice());
+    return out;
+}
 
-    pub fn write(&self, s: &str) {
-        let mut out_file = self.out_file.borrow_mut();
-        out_file.flush();
-        // TODO well.  should be a bit more flexible, i guess.
-        out_file.write_str(s);
-        out_file.flush();
-    }
+/** Returns whichever sequence is shorter, preferring the first on a tie. */
+fn shorter(a: Option<Vec<u8>>, b: Option<Vec<u8>>) -> Option<Vec<u8>> {
+    return match (a, b) {
+        (Some(a), Some(b)) => Some(if b.len() < a.len() { b } else { a }),
+        (Some(a), None) => Some(a),
+        (None, b) => b,
+    };
+}
 
 
+////////////////////////////////////////////////////////////////////////////////
+// Attributes
+
+/** The shape of an underline.  Anything besides `Single` needs `Smulx`, and
+ * falls back to a plain underline without it. */
+#[derive(Clone, Copy, PartialEq, Show)]
+pub enum UnderlineStyle {
+    Single,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
 
-    // Some stuff
-    pub fn reposition(&self, x: usize, y: usize) {
-        // TODO check for existence of cup
-        self.write_cap2("cup", y as isize, x as isize);
+impl UnderlineStyle {
+    /** Returns the argument `Smulx` takes for this style. */
+    pub fn code(&self) -> isize {
+        return match *self {
+            UnderlineStyle::Single => 1,
+            UnderlineStyle::Double => 2,
+            UnderlineStyle::Curly => 3,
+            UnderlineStyle::Dotted => 4,
+            UnderlineStyle::Dashed => 5,
+        };
     }
 }
 
+/** A link to somewhere else, via OSC 8.  Terminals that understand it make
+ * the text clickable; the rest are supposed to ignore it.
+ *
+ * Cells with the same id and URI count as the same link, e.g. for
+ * highlighting on hover, even if they're written out separately.  A link
+ * without an id gets one when it's written to a canvas.
+ */
+#[derive(Clone, PartialEq, Show)]
+pub struct Hyperlink {
+    pub uri: String,
+    pub id: Option<String>,
+}
 
-////////////////////////////////////////////////////////////////////////////////
-// Attributes
+static NEXT_LINK_ID: AtomicUsize = ATOMIC_USIZE_INIT;
 
-#[derive(Clone)]
+impl Hyperlink {
+    /** Returns a copy of this link with a fresh id, unique to this process. */
+    pub fn with_generated_id(&self) -> Hyperlink {
+        let n = NEXT_LINK_ID.fetch_add(1, Ordering::SeqCst);
+        let pid = unsafe { libc::getpid() };
+        return Hyperlink{
+            uri: self.uri.clone(),
+            id: Some(format!("amulet-{}-{}", pid, n)),
+        };
+    }
+}
+
+#[derive(Clone, PartialEq)]
 pub struct Style {
     // TODO i guess these could be compacted into a bitstring, but eh.
     pub is_bold: bool,
     pub is_underline: bool,
-
-    // TODO strictly speaking these should refer to entire colors, not just
-    // color numbers, for compatability with a truckload of other kinds of
-    // terminals.  but, you know.
-    // TODO -1 for the default is super hokey and only for curses compat
-    pub fg_color: isize,
-    pub bg_color: isize,
+    pub is_italic: bool,
+    pub is_reverse: bool,
+    pub is_dim: bool,
+    pub is_blink: bool,
+    pub is_strikethrough: bool,
+    pub is_invisible: bool,
+    /** There's no standard capability for this, so it only works if the
+     * terminal has tmux's `Smol`. */
+    pub is_overline: bool,
+
+    pub fg_color: Color,
+    pub bg_color: Color,
+
+    /** Only matters if `is_underline` is set. */
+    pub underline_style: UnderlineStyle,
+    /** Default means the same as the text.  Needs `Setulc`. */
+    pub underline_color: Color,
+
+    pub hyperlink: Option<Rc<Hyperlink>>,
 }
 pub fn Style() -> Style {
-    return Style{ ..NORMAL };
+    return Style{
+        is_bold: false,
+        is_underline: false,
+        is_italic: false,
+        is_reverse: false,
+        is_dim: false,
+        is_blink: false,
+        is_strikethrough: false,
+        is_invisible: false,
+        is_overline: false,
+        fg_color: Color::Default,
+        bg_color: Color::Default,
+        underline_style: UnderlineStyle::Single,
+        underline_color: Color::Default,
+        hyperlink: None,
+    };
 }
 impl Style {
     pub fn bold(&self) -> Style {
-        return Style{ is_bold: true, ..*self };
+        return Style{ is_bold: true, ..self.clone() };
     }
 
     pub fn underline(&self) -> Style {
-        return Style{ is_underline: true, ..*self };
+        return Style{ is_underline: true, ..self.clone() };
+    }
+
+    /** Underline, with a fancier line if the terminal supports it. */
+    pub fn underline_style(&self, underline_style: UnderlineStyle) -> Style {
+        return Style{ is_underline: true, underline_style: underline_style, ..self.clone() };
+    }
+
+    /** Sets the color of the underline, if the terminal supports it.  Doesn't
+     * turn on underlining by itself. */
+    pub fn underline_color(&self, color: Color) -> Style {
+        return Style{ underline_color: color, ..self.clone() };
+    }
+
+    pub fn italic(&self) -> Style {
+        return Style{ is_italic: true, ..self.clone() };
+    }
+
+    pub fn reverse(&self) -> Style {
+        return Style{ is_reverse: true, ..self.clone() };
+    }
+
+    pub fn dim(&self) -> Style {
+        return Style{ is_dim: true, ..self.clone() };
+    }
+
+    pub fn blink(&self) -> Style {
+        return Style{ is_blink: true, ..self.clone() };
+    }
+
+    pub fn strikethrough(&self) -> Style {
+        return Style{ is_strikethrough: true, ..self.clone() };
+    }
+
+    pub fn invisible(&self) -> Style {
+        return Style{ is_invisible: true, ..self.clone() };
+    }
+
+    pub fn overline(&self) -> Style {
+        return Style{ is_overline: true, ..self.clone() };
+    }
+
+    /** Parses a description of a style, like "bold underline red on
+     * bright_black" or "italic #ff8800 on blue".
+     *
+     * Each word is either an attribute, as for `with_attribute()`, or a
+     * color, as for `Color::parse()`.  A color on its own is for the text;
+     * after `on`, or with `on_` stuck to the front as in blessings, it's for
+     * the background; after `underline_color`, it's for the underline.
+     * "normal" on its own means the default style.  Hyperlinks can't be
+     * written this way.
+     */
+    pub fn parse(spec: &str) -> Result<Style, StyleParseError> {
+        let mut style = Style();
+        let mut seen_fg = false;
+        let mut seen_bg = false;
+        let mut seen_underline = false;
+
+        let mut words = spec.words();
+        loop {
+            let word = match words.next() {
+                Some(word) => word,
+                None => break,
+            };
+            if word == "normal" {
+                continue;
+            }
+            match style.with_attribute(word) {
+                Some(new_style) => {
+                    style = new_style;
+                    continue;
+                }
+                None => (),
+            }
+
+            // Anything else has to be a color; figure out which one
+            let (target, color_word) = if word == "on" || word == "underline_color" {
+                match words.next() {
+                    Some(next) => (word, next),
+                    None => return Err(StyleParseError::MissingColor(word.to_string())),
+                }
+            }
+            else if word.starts_with("on_") {
+                ("on", word.slice_from(3))
+            }
+            else {
+                ("", word)
+            };
+
+            let color = match Color::parse(color_word) {
+                Some(color) => color,
+                None if target.is_empty() => return Err(StyleParseError::UnknownWord(word.to_string())),
+                None => return Err(StyleParseError::UnknownColor(color_word.to_string())),
+            };
+            let (seen, what) = match target {
+                "on" => (&mut seen_bg, "background"),
+                "underline_color" => (&mut seen_underline, "underline"),
+                _ => (&mut seen_fg, "text"),
+            };
+            if *seen {
+                return Err(StyleParseError::RepeatedColor(what));
+            }
+            *seen = true;
+
+            style = match target {
+                "on" => style.bg(color),
+                "underline_color" => style.underline_color(color),
+                _ => style.fg(color),
+            };
+        }
+
+        return Ok(style);
+    }
+
+    /** Turns on an attribute by name: one of the builder methods above, or
+     * an underline style like "curly_underline".  Returns None if there's no
+     * such attribute. */
+    pub fn with_attribute(&self, name: &str) -> Option<Style> {
+        return Some(match name {
+            "bold" => self.bold(),
+            "underline" => self.underline(),
+            "double_underline" => self.underline_style(UnderlineStyle::Double),
+            "curly_underline" => self.underline_style(UnderlineStyle::Curly),
+            "dotted_underline" => self.underline_style(UnderlineStyle::Dotted),
+            "dashed_underline" => self.underline_style(UnderlineStyle::Dashed),
+            "italic" => self.italic(),
+            "reverse" => self.reverse(),
+            "dim" => self.dim(),
+            "blink" => self.blink(),
+            "strikethrough" => self.strikethrough(),
+            "invisible" => self.invisible(),
+            "overline" => self.overline(),
+            _ => return None,
+        });
+    }
+
+    /** Returns true if this style has any attribute turned on that `other`
+     * doesn't.  Colors don't count. */
+    pub fn has_attributes_beyond(&self, other: &Style) -> bool {
+        return (self.is_bold && ! other.is_bold)
+            || (self.is_underline && ! other.is_underline)
+            || (self.is_italic && ! other.is_italic)
+            || (self.is_reverse && ! other.is_reverse)
+            || (self.is_dim && ! other.is_dim)
+            || (self.is_blink && ! other.is_blink)
+            || (self.is_strikethrough && ! other.is_strikethrough)
+            || (self.is_invisible && ! other.is_invisible)
+            || (self.is_overline && ! other.is_overline);
+    }
+
+    pub fn fg(&self, color: Color) -> Style {
+        return Style{ fg_color: color, ..self.clone() };
+    }
+    pub fn bg(&self, color: Color) -> Style {
+        return Style{ bg_color: color, ..self.clone() };
     }
 
-    // TODO this pretty much blows; color pairs are super archaic and i am
-    // trying to hack around them until i just give up and bail on the curses
-    // dependency.  works on my machine...
-    // TODO this only works for the first 16 colors anyway
-    // TODO calling start_color() resets all color pairs, so you can't use this
-    // before capturing the window...  :|
-    // TODO this doesn't handle default colors correctly, because those are
-    // color index -1.
-    pub fn fg(&self, color: isize) -> Style {
-        return Style{ fg_color: color, ..*self };
+    /** Makes the text a link to `uri`.  The URI should already be encoded;
+     * anything that isn't printable ASCII is dropped. */
+    pub fn link(&self, uri: &str) -> Style {
+        let link = Hyperlink{ uri: uri.to_string(), id: None };
+        return Style{ hyperlink: Some(Rc::new(link)), ..self.clone() };
     }
-    pub fn bg(&self, color: isize) -> Style {
-        return Style{ bg_color: color, ..*self };
+
+    /** Same as `link()`, but with an explicit id, for tying together pieces
+     * of the same link that aren't next to each other.  The id can't contain
+     * semicolons or colons. */
+    pub fn link_with_id(&self, uri: &str, id: &str) -> Style {
+        let link = Hyperlink{ uri: uri.to_string(), id: Some(id.to_string()) };
+        return Style{ hyperlink: Some(Rc::new(link)), ..self.clone() };
     }
+}
 
-    #[fixed_stack_segment]
-    fn c_value(&self) -> c_int {
-        let mut rv: c_int = 0;
 
+/** Writes the style the way `Style::parse()` reads it, minus any link. */
+impl fmt::String for Style {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut words = vec![];
         if self.is_bold {
-            rv |= c::A_BOLD;
+            words.push("bold".to_string());
         }
         if self.is_underline {
-            rv |= c::A_UNDERLINE;
-        }
-        
-        // Calculate a pair number to consume.  It's a signed short, so use the
-        // lower 8 bits for fg and upper 7 for bg
-        // TODO without ext_colors, this gets all fucked up if the pair number
-        // is anything over 255
-        let fg = match self.fg_color {
-            -1 => 14,
-            _ => self.fg_color,
-        };
-        let bg = match self.bg_color {
-            -1 => 14,
-            _ => self.bg_color,
+            let name = match self.underline_style {
+                UnderlineStyle::Single => "underline",
+                UnderlineStyle::Double => "double_underline",
+                UnderlineStyle::Curly => "curly_underline",
+                UnderlineStyle::Dotted => "dotted_underline",
+                UnderlineStyle::Dashed => "dashed_underline",
+            };
+            words.push(name.to_string());
+        }
+        if self.is_italic {
+            words.push("italic".to_string());
+        }
+        if self.is_reverse {
+            words.push("reverse".to_string());
+        }
+        if self.is_dim {
+            words.push("dim".to_string());
+        }
+        if self.is_blink {
+            words.push("blink".to_string());
+        }
+        if self.is_strikethrough {
+            words.push("strikethrough".to_string());
+        }
+        if self.is_invisible {
+            words.push("invisible".to_string());
+        }
+        if self.is_overline {
+            words.push("overline".to_string());
+        }
+
+        if self.fg_color != Color::Default {
+            words.push(format!("{}", self.fg_color));
+        }
+        if self.bg_color != Color::Default {
+            words.push(format!("on {}", self.bg_color));
+        }
+        if self.underline_color != Color::Default {
+            words.push(format!("underline_color {}", self.underline_color));
+        }
+
+        if words.is_empty() {
+            return write!(f, "normal");
+        }
+        return write!(f, "{}", words.connect(" "));
+    }
+}
+
+/** What `Style::parse()` didn't like. */
+#[derive(Clone, PartialEq, Show)]
+pub enum StyleParseError {
+    /** A word that's neither an attribute nor a color. */
+    UnknownWord(String),
+    /** Something that should've been a color, e.g. after "on", isn't. */
+    UnknownColor(String),
+    /** "on" or "underline_color" came last, with no color after it. */
+    MissingColor(String),
+    /** Two colors for the same thing; says which thing. */
+    RepeatedColor(&'static str),
+}
+
+impl fmt::String for StyleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match *self {
+            StyleParseError::UnknownWord(ref word) => write!(f, "{} isn't an attribute or a color", word),
+            StyleParseError::UnknownColor(ref word) => write!(f, "unknown color {}", word),
+            StyleParseError::MissingColor(ref word) => write!(f, "expected a color after {}", word),
+            StyleParseError::RepeatedColor(what) => write!(f, "more than one {} color", what),
         };
-        //let pair = ((bg << 4) | fg) as c_short;
-        let pair = fg as c_short;
-        unsafe {
-            c::init_pair(pair, self.fg_color as c_short, self.bg_color as c_short);
-            rv |= c::COLOR_PAIR(pair as c_int);
+    }
+}
+
+impl Error for StyleParseError {
+    fn description(&self) -> &str {
+        return "invalid style";
+    }
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+// Text
+
+static ZERO_WIDTH_JOINER: char = '\u{200d}';
+
+/** Splits text into grapheme clusters: what a reader would call single
+ * characters, like a letter and its accents.  Emoji strung together with
+ * zero-width joiners, which Unicode splits up, are kept as one cluster, the
+ * way terminals that support them draw them. */
+pub fn grapheme_clusters(s: &str) -> Vec<&str> {
+    let mut clusters = vec![];
+    let mut start = 0;
+    let mut joined = false;
+    for (i, cluster) in s.grapheme_indices(true) {
+        if i > start && ! joined {
+            clusters.push(s.slice(start, i));
+            start = i;
         }
+        joined = cluster.chars().next_back() == Some(ZERO_WIDTH_JOINER);
+    }
+    if start < s.len() {
+        clusters.push(s.slice_from(start));
+    }
+    return clusters;
+}
 
-        return rv;
+/** Returns how many columns a grapheme cluster takes up on the screen: 2 for
+ * wide characters like CJK ideographs, most emoji, and flags, 0 for combining
+ * marks with nothing to combine with, and 1 for everything else.  Returns None
+ * for control characters, which aren't drawn at all. */
+pub fn cluster_width(cluster: &str) -> Option<usize> {
+    let mut width = 0;
+    let mut regional_indicators = 0us;
+    for ch in cluster.chars() {
+        // width() calls NUL zero-width, but it's a control character too
+        if ch == '\0' {
+            return None;
+        }
+        if '\u{1f1e6}' <= ch && ch <= '\u{1f1ff}' {
+            regional_indicators += 1;
+        }
+        match ch.width(false) {
+            Some(ch_width) => width = max(width, ch_width),
+            None => return None,
+        }
+    }
+    // Each regional indicator is narrow by itself, but a pair of them is a
+    // flag, which terminals draw as wide as any other emoji
+    if regional_indicators >= 2 {
+        return Some(2);
     }
+    return Some(min(width, 2));
 }
 
-pub static NORMAL: Style = Style{ is_bold: false, is_underline: false, fg_color: -1, bg_color: -1 };
+/** Returns how many columns some text takes up on the screen, assuming it
+ * doesn't wrap.  Control characters are counted as taking up nothing. */
+pub fn text_width(s: &str) -> usize {
+    let mut width = 0;
+    for cluster in grapheme_clusters(s).iter() {
+        width += cluster_width(*cluster).unwrap_or(0);
+    }
+    return width;
+}
 
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -437,6 +1443,9 @@ pub enum Key {
     Character(char),
     SpecialKey(SpecialKeyCode),
     FunctionKey(u32),
+    /** Not actually a key: the terminal changed size.  Has the new number of
+     * rows and columns. */
+    Resize(usize, usize),
 }
 
 fn cap_to_key(cap: &str) -> Key {
@@ -0,0 +1,94 @@
+/** Named styles, so widgets can ask for "header" or "error" and leave the
+ * actual colors up to the application (or the user).
+ *
+ * Like Urwid's palettes, each name can be defined differently depending on
+ * how many colors the terminal has: a 256-color gradient can fall back to
+ * plain blue on a 16-color terminal, and to reverse video on a monochrome
+ * one.
+ */
+
+use std::collections::HashMap;
+
+use color::ColorDepth;
+use ll::Style;
+
+/** The definitions of a single name, one slot per `ColorDepth`. */
+#[derive(Clone)]
+struct PaletteEntry {
+    styles: Vec<Option<Style>>,
+}
+
+impl PaletteEntry {
+    /** Returns the definition meant for the deepest color depth the terminal
+     * can handle.  If there isn't one, the shallowest definition is used,
+     * since colors the terminal can't show will be downsampled anyway. */
+    fn resolve(&self, depth: ColorDepth) -> &Style {
+        let depth = depth as usize;
+        for i in range(0, depth + 1).rev() {
+            match self.styles[i] {
+                Some(ref style) => return style,
+                None => (),
+            }
+        }
+        for i in range(depth + 1, self.styles.len()) {
+            match self.styles[i] {
+                Some(ref style) => return style,
+                None => (),
+            }
+        }
+        // Entries are only ever created with at least one style
+        unreachable!();
+    }
+}
+
+#[derive(Clone)]
+pub struct Palette {
+    entries: HashMap<String, PaletteEntry>,
+}
+
+impl Palette {
+    pub fn new() -> Palette {
+        return Palette{ entries: HashMap::new() };
+    }
+
+    /** Defines `name` as `style`, on every terminal.  Replaces any existing
+     * definitions for that name. */
+    pub fn set(&mut self, name: &str, style: Style) {
+        let mut entry = PaletteEntry{ styles: vec![None, None, None, None] };
+        entry.styles[0] = Some(style);
+        self.entries.insert(name.to_string(), entry);
+    }
+
+    /** Defines `name` as `style` on terminals with at least `depth` colors,
+     * leaving alone any definitions for other depths. */
+    pub fn set_for_depth(&mut self, name: &str, depth: ColorDepth, style: Style) {
+        if ! self.entries.contains_key(name) {
+            self.entries.insert(name.to_string(), PaletteEntry{ styles: vec![None, None, None, None] });
+        }
+        self.entries.get_mut(name).unwrap().styles[depth as usize] = Some(style);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        return self.entries.contains_key(name);
+    }
+
+    /** Returns the style `name` should have on a terminal with the given
+     * color depth, or None if it's not defined at all. */
+    pub fn get(&self, name: &str, depth: ColorDepth) -> Option<&Style> {
+        return self.entries.get(name).map(|entry| entry.resolve(depth));
+    }
+
+    /** Same as `get()`, but undefined names get the default style, as they do
+     * in Urwid. */
+    pub fn resolve(&self, name: &str, depth: ColorDepth) -> Style {
+        return match self.get(name, depth) {
+            Some(style) => style.clone(),
+            None => Style(),
+        };
+    }
+
+    /** Returns all the defined names, in no particular order. */
+    pub fn names(&self) -> Vec<&str> {
+        return self.entries.keys().map(|name| name.as_slice()).collect();
+    }
+}
@@ -0,0 +1,358 @@
+/** Tracks what the terminal is currently drawing with, and works out the
+ * cheapest way to switch to something else.
+ *
+ * Terminals make this harder than it ought to be.  Most attributes can only be
+ * turned on, not off; the only general way to turn one off is `sgr0`, which
+ * turns off everything, colors included.  `sgr` can set several attributes at
+ * once, but also tends to reset the rest.  So there are three ways to get
+ * from one style to another, and which is shortest depends on both the
+ * styles and the terminal.
+ */
+
+use color::{Color,index_to_rgb};
+use ll::{Hyperlink,Style,TerminalInfo,UnderlineStyle};
+use tparm::Param;
+
+// Every attribute, in the order used by attributes() below
+static ENTER_CAPS: [&'static str; 9] = [
+    "bold", "smul", "sitm", "rev", "dim", "blink", "smxx", "invis", "Smol",
+];
+// The few attributes that can be turned off on their own
+static EXIT_CAPS: [Option<&'static str>; 9] = [
+    None, Some("rmul"), Some("ritm"), None, None, None, Some("rmxx"), None, None,
+];
+// Which attributes `sgr` can set: underline, reverse, blink, dim, bold, and
+// invisible.  It also takes standout, protected, and the alternate character
+// set, none of which Style has
+static SGR_COVERS: [bool; 9] = [
+    true, true, false, true, true, true, false, true, false,
+];
+
+fn attributes(style: &Style) -> [bool; 9] {
+    return [
+        style.is_bold,
+        style.is_underline,
+        style.is_italic,
+        style.is_reverse,
+        style.is_dim,
+        style.is_blink,
+        style.is_strikethrough,
+        style.is_invisible,
+        style.is_overline,
+    ];
+}
+
+
+pub struct Pen {
+    current: Style,
+}
+
+impl Pen {
+    /** Creates a pen for a terminal that's drawing with the default style,
+     * e.g. one that's just been sent `sgr0`. */
+    pub fn new() -> Pen {
+        return Pen{ current: Style() };
+    }
+
+    /** Returns the style the terminal is currently drawing with. */
+    pub fn style(&self) -> &Style {
+        return &self.current;
+    }
+
+    /** Records that the terminal has been reset to the default style by some
+     * other means. */
+    pub fn reset(&mut self) {
+        self.current = Style();
+    }
+
+    /** Switches the terminal to drawing with `style`, as cheaply as
+     * possible. */
+    pub fn change(&mut self, terminfo: &TerminalInfo, style: &Style) {
+        let sequence = self.transition(terminfo, style);
+        if ! sequence.is_empty() {
+            terminfo.write_formatted(sequence.as_slice());
+        }
+        self.current = effective_style(terminfo, style);
+    }
+
+    /** Returns the shortest sequence that switches the terminal from the
+     * current style to `style`.  Anything the terminal can't do is left
+     * out. */
+    pub fn transition(&self, terminfo: &TerminalInfo, style: &Style) -> Vec<u8> {
+        let style = &effective_style(terminfo, style);
+        // By far the most common case, when painting a run of cells
+        if *style == self.current {
+            return vec![];
+        }
+
+        // On a tie, the earlier candidate wins
+        let candidates = vec![
+            self.incrementally(terminfo, style),
+            self.via_reset(terminfo, style),
+            self.via_sgr(terminfo, style),
+        ];
+        let mut best: Option<Vec<u8>> = None;
+        for candidate in candidates.into_iter() {
+            let sequence = match candidate {
+                Some(sequence) => sequence,
+                None => continue,
+            };
+            let is_better = match best {
+                Some(ref best_sequence) => sequence.len() < best_sequence.len(),
+                None => true,
+            };
+            if is_better {
+                best = Some(sequence);
+            }
+        }
+
+        let mut best = match best {
+            Some(sequence) => sequence,
+            None => self.best_effort(terminfo, style),
+        };
+
+        // Links aren't attributes, so none of the above touches them
+        change_link(&self.current, style, &mut best);
+
+        return best;
+    }
+
+    /** Turns off only what needs turning off, then turns on only what needs
+     * turning on.  Impossible if an attribute with no exit capability has to
+     * go. */
+    fn incrementally(&self, terminfo: &TerminalInfo, style: &Style) -> Option<Vec<u8>> {
+        let mut out = vec![];
+        let from = attributes(&self.current);
+        let to = attributes(style);
+
+        for i in range(0, from.len()) {
+            if from[i] && ! to[i] {
+                let exit = match EXIT_CAPS[i] {
+                    Some(cap) => terminfo.format_cap(cap, &[]),
+                    None => None,
+                };
+                match exit {
+                    Some(sequence) => out.push_all(sequence.as_slice()),
+                    None => return None,
+                }
+            }
+        }
+
+        enter_attributes(terminfo, &from, &to, &mut out);
+        change_underline(terminfo, &self.current, style, &mut out);
+        change_colors(terminfo, &self.current, style, &mut out);
+        return Some(out);
+    }
+
+    /** Starts over with `sgr0`, then builds the new style from scratch.
+     * Impossible if there's something to reset and no `sgr0` to do it. */
+    fn via_reset(&self, terminfo: &TerminalInfo, style: &Style) -> Option<Vec<u8>> {
+        let mut out = vec![];
+        let from = attributes(&self.current);
+        let to = attributes(style);
+
+        // Not needed at all if the current style is already the default
+        let default = Style();
+        let mut base = &self.current;
+        if from.iter().any(|&on| on)
+            || self.current.fg_color != Color::Default
+            || self.current.bg_color != Color::Default
+        {
+            match terminfo.format_cap("sgr0", &[]) {
+                Some(sequence) => {
+                    out.push_all(sequence.as_slice());
+                    base = &default;
+                }
+                None => return None,
+            }
+        }
+
+        enter_attributes(terminfo, &attributes(base), &to, &mut out);
+        change_underline(terminfo, base, style, &mut out);
+        change_colors(terminfo, base, style, &mut out);
+        return Some(out);
+    }
+
+    /** Sets most of the attributes in one go with `sgr`, then adds whatever
+     * it doesn't cover.  Only used when `sgr` starts with SGR 0, so it's
+     * known to reset everything else, colors included -- which it does on
+     * every terminal anyone uses. */
+    fn via_sgr(&self, terminfo: &TerminalInfo, style: &Style) -> Option<Vec<u8>> {
+        let to = attributes(style);
+        let params = [
+            Param::Number(0),   // standout
+            sgr_flag(style.is_underline),
+            sgr_flag(style.is_reverse),
+            sgr_flag(style.is_blink),
+            sgr_flag(style.is_dim),
+            sgr_flag(style.is_bold),
+            sgr_flag(style.is_invisible),
+            Param::Number(0),   // protected
+            Param::Number(0),   // alternate character set
+        ];
+        let mut out = match terminfo.format_cap("sgr", &params) {
+            Some(sequence) => sequence,
+            None => return None,
+        };
+        // Most terminals pick the character set first, too
+        let start = if out.starts_with(b"\x1b(B") { 3 } else { 0 };
+        if ! out.slice_from(start).starts_with(b"\x1b[0") {
+            return None;
+        }
+
+        // Pretend sgr already did the rest, so enter_attributes skips it
+        let mut covered = [false; 9];
+        for i in range(0, to.len()) {
+            covered[i] = to[i] && SGR_COVERS[i];
+        }
+        enter_attributes(terminfo, &covered, &to, &mut out);
+
+        let default = Style();
+        change_underline(terminfo, &default, style, &mut out);
+        change_colors(terminfo, &default, style, &mut out);
+        return Some(out);
+    }
+
+    /** Turns on what needs turning on and leaves everything else alone.  The
+     * last resort, for when an attribute has to go and the terminal has no
+     * way to get rid of it. */
+    fn best_effort(&self, terminfo: &TerminalInfo, style: &Style) -> Vec<u8> {
+        let mut out = vec![];
+        enter_attributes(terminfo, &attributes(&self.current), &attributes(style), &mut out);
+        change_underline(terminfo, &self.current, style, &mut out);
+        change_colors(terminfo, &self.current, style, &mut out);
+        return out;
+    }
+}
+
+
+/** Returns the style the terminal will actually end up using, once the parts
+ * it can't do are dropped.  Keeps the pen from trying to change, say, the
+ * underline color over and over on a terminal that doesn't have one. */
+fn effective_style(terminfo: &TerminalInfo, style: &Style) -> Style {
+    let mut style = style.clone();
+    if ! style.is_underline || ! terminfo.features.styled_underline {
+        style.underline_style = UnderlineStyle::Single;
+    }
+    if ! terminfo.features.underline_color {
+        style.underline_color = Color::Default;
+    }
+    return style;
+}
+
+fn sgr_flag(on: bool) -> Param {
+    return Param::Number(on as isize);
+}
+
+/** Adds the sequences that turn on everything in `to` that isn't in `from`. */
+fn enter_attributes(terminfo: &TerminalInfo, from: &[bool; 9], to: &[bool; 9], out: &mut Vec<u8>) {
+    for i in range(0, to.len()) {
+        if to[i] && ! from[i] {
+            match terminfo.format_cap(ENTER_CAPS[i], &[]) {
+                Some(sequence) => out.push_all(sequence.as_slice()),
+                None => (),
+            }
+        }
+    }
+}
+
+/** Adds the sequences that change the underline's shape and color, assuming
+ * the underline itself has already been turned on or off as necessary. */
+fn change_underline(terminfo: &TerminalInfo, from: &Style, to: &Style, out: &mut Vec<u8>) {
+    // If the underline was just turned on, it's a plain one
+    let from_style = if from.is_underline { from.underline_style } else { UnderlineStyle::Single };
+    if to.is_underline && to.underline_style != from_style {
+        let sequence = match to.underline_style {
+            UnderlineStyle::Single => terminfo.format_cap("smul", &[]),
+            _ => terminfo.format_cap("Smulx", &[Param::Number(to.underline_style.code())]),
+        };
+        match sequence {
+            Some(sequence) => out.push_all(sequence.as_slice()),
+            None => (),
+        }
+    }
+
+    if to.underline_color != from.underline_color {
+        let rgb = match to.underline_color {
+            Color::Default => {
+                // No capability for this one; SGR 59 is the only way
+                out.push_all(b"\x1b[59m");
+                return;
+            }
+            Color::Rgb(r, g, b) => (r, g, b),
+            color => index_to_rgb(color.index().unwrap()),
+        };
+        let (r, g, b) = rgb;
+        let packed = ((r as isize) << 16) | ((g as isize) << 8) | (b as isize);
+        match terminfo.format_cap("Setulc", &[Param::Number(packed)]) {
+            Some(sequence) => out.push_all(sequence.as_slice()),
+            None => (),
+        }
+    }
+}
+
+/** Adds the sequence that starts, ends, or switches to another link.  There's
+ * no capability for this, but terminals that don't know OSC 8 should ignore
+ * it, as they do any operating system command they don't understand. */
+fn change_link(from: &Style, to: &Style, out: &mut Vec<u8>) {
+    if to.hyperlink == from.hyperlink {
+        return;
+    }
+
+    out.push_all(b"\x1b]8;");
+    match to.hyperlink {
+        Some(ref link) => push_link(&**link, out),
+        // An empty URI ends the link
+        None => out.push(b';'),
+    }
+    out.push_all(b"\x1b\\");
+}
+
+fn push_link(link: &Hyperlink, out: &mut Vec<u8>) {
+    match link.id {
+        Some(ref id) => {
+            out.push_all(b"id=");
+            push_printable(id.as_slice(), out);
+        }
+        None => (),
+    }
+    out.push(b';');
+    push_printable(link.uri.as_slice(), out);
+}
+
+/** Adds only the printable ASCII from `s`, so a stray escape in a URI can't end
+ * the sequence early and do something else entirely.  `$` is percent-encoded
+ * too, since the whole sequence goes out through tputs, which would take a
+ * `$<...>` as padding. */
+fn push_printable(s: &str, out: &mut Vec<u8>) {
+    for &byte in s.as_bytes().iter() {
+        if byte == b'$' {
+            out.push_all(b"%24");
+        }
+        else if 32 <= byte && byte <= 126 {
+            out.push(byte);
+        }
+    }
+}
+
+/** Adds the sequences that change the colors from `from`'s to `to`'s. */
+fn change_colors(terminfo: &TerminalInfo, from: &Style, to: &Style, out: &mut Vec<u8>) {
+    let mut bg = from.bg_color;
+    if to.fg_color != from.fg_color {
+        match terminfo.color_sequence(to.fg_color, false) {
+            Some(sequence) => out.push_all(sequence.as_slice()),
+            None => (),
+        }
+        // Without setaf, resetting to the default is done with op, which
+        // resets the background as well
+        if to.fg_color == Color::Default && ! terminfo.has_cap("setaf") {
+            bg = Color::Default;
+        }
+    }
+    if to.bg_color != bg {
+        match terminfo.color_sequence(to.bg_color, true) {
+            Some(sequence) => out.push_all(sequence.as_slice()),
+            None => (),
+        }
+    }
+}
@@ -1,18 +1,76 @@
+use libc::c_int;
+
 use canvas::Canvas;
-use ll::Style;
+use color::DistanceMetric;
+use ll::{SetupError,Style};
 use ll::TerminalInfo;
-use termios;
 
 pub struct Terminal<'a> {
     info: TerminalInfo<'a>,
 }
 
-impl<'a> Terminal<'a> {
-    pub fn new() -> Terminal<'a> {
-        let info = TerminalInfo::new();
+/** Options for creating a Terminal.  Get one from `Terminal::builder()`.
+ *
+ * By default, you get the same thing as `Terminal::new()`: stdin and stdout,
+ * with the terminal type taken from TERM.  But you can, say, open /dev/tty
+ * yourself and drive that while stdout goes to a pipe.
+ */
+pub struct TerminalBuilder {
+    term_name: Option<String>,
+    in_fd: c_int,
+    out_fd: c_int,
+    distance_metric: Option<DistanceMetric>,
+}
+
+impl TerminalBuilder {
+    /** Use a particular terminal type, rather than whatever TERM says. */
+    pub fn term(self, term_name: &str) -> TerminalBuilder {
+        return TerminalBuilder{ term_name: Some(term_name.to_string()), ..self };
+    }
+
+    /** Read keypresses from this fd, rather than stdin. */
+    pub fn input(self, fd: c_int) -> TerminalBuilder {
+        return TerminalBuilder{ in_fd: fd, ..self };
+    }
+
+    /** Write to this fd, rather than stdout. */
+    pub fn output(self, fd: c_int) -> TerminalBuilder {
+        return TerminalBuilder{ out_fd: fd, ..self };
+    }
+
+    /** Match colors the terminal can't show to ones it can using this
+     * metric, rather than the default. */
+    pub fn distance_metric(self, metric: DistanceMetric) -> TerminalBuilder {
+        return TerminalBuilder{ distance_metric: Some(metric), ..self };
+    }
 
-        return Terminal{
+    pub fn build<'a>(self) -> Result<Terminal<'a>, SetupError> {
+        let term_name = self.term_name.as_ref().map(|name| name.as_slice());
+        let info = try!(TerminalInfo::open(term_name, self.in_fd, self.out_fd));
+        match self.distance_metric {
+            Some(metric) => info.set_distance_metric(metric),
+            None => (),
+        }
+
+        return Ok(Terminal{
             info: info,
+        });
+    }
+}
+
+impl<'a> Terminal<'a> {
+    /** Sets up the terminal on stdin and stdout.  Fails if either isn't a
+     * terminal, or if terminfo doesn't know how to drive it. */
+    pub fn new() -> Result<Terminal<'a>, SetupError> {
+        return Terminal::builder().build();
+    }
+
+    pub fn builder() -> TerminalBuilder {
+        return TerminalBuilder{
+            term_name: None,
+            in_fd: 0,
+            out_fd: 1,
+            distance_metric: None,
         };
     }
 
@@ -29,30 +87,38 @@ impl<'a> Terminal<'a> {
         return self.info.width();
     }
 
+    /** Changes how colors the terminal can't show are matched to ones it
+     * can.  Takes effect the next time anything is drawn. */
+    pub fn set_distance_metric(&self, metric: DistanceMetric) {
+        self.info.set_distance_metric(metric);
+    }
+
 
     pub fn at<F: Fn() -> ()>(&self, x: usize, y: usize, cb: F) {
-        self.info.write_cap("sc");  // save cursor
-        // TODO check for existence of cup
-        self.info.write_cap2("cup", y as isize, x as isize);
+        // If the terminal can't save the cursor, it just stays wherever the
+        // callback leaves it
+        self.info.save_cursor();
+        self.info.reposition(x, y);
 
         cb();
 
-        self.info.write_cap("rc");  // restore cursor
+        self.info.restore_cursor();
+        self.info.flush();
     }
 
     // Output
+    // Unlike a Canvas, these go straight to the terminal.
 
     #[inline]
     pub fn write(&self, s: &str) {
         self.info.write(s);
+        self.info.flush();
     }
 
     pub fn attrwrite(&self, s: &str, style: Style) {
         // TODO try to cut down on the amount of back-and-forth between c
         // strings and rust strings all up in here
-        if style.is_underline {
-            self.info.write_cap("smul");
-        }
+        self.info.enter_attributes(&style, &Style());
 
         // TODO this may need some escaping or whatever -- or maybe that
         // belongs in write()
@@ -60,29 +126,29 @@ impl<'a> Terminal<'a> {
 
         // Clean up after ourselves: reset style to default
         // TODO this is ripe for some optimizing
-        self.info.write_cap("sgr0");
+        self.info.exit_attribute_mode();
+        self.info.flush();
     }
 
     // Full-screen
 
     pub fn fullscreen_canvas(&'a self, cb: &fn(&mut Canvas)) {
         // Enter fullscreen
-        let _tidy_cup = self.info.write_tidy_cap("smcup", "rmcup");
+        let _tidy_cup = self.info.write_tidy_cap(TerminalInfo::enter_ca_mode, TerminalInfo::exit_ca_mode);
 
         // Enable keypad mode
-        let _tidy_kx = self.info.write_tidy_cap("smkx", "rmkx");
+        let _tidy_kx = self.info.write_tidy_cap(TerminalInfo::keypad_xmit, TerminalInfo::keypad_local);
 
         // And clear the screen first
-        self.info.write_cap("clear");
+        self.info.clear_screen();
+        self.info.flush();
 
         // TODO intrflush, or is that a curses thing?
 
         // TODO so, we need to switch to raw mode *some*where.  is this an
         // appropriate place?  i assume if you have a fullscreen app then you
         // want to get keypresses.
-        // TODO seems weird to create a second one of these.  stick a
-        // .checkpoint() on the one attached to the terminal?
-        let mut tidy_termstate = termios::TidyTerminalState(self.info.in_fd);
+        let mut tidy_termstate = self.info.checkpoint_termstate();
         tidy_termstate.cbreak();
 
         let mut canv = Canvas(&self.info, 0, 0, self.height(), self.width());
@@ -95,13 +161,14 @@ impl<'a> Terminal<'a> {
     pub fn enter_fullscreen(&'a mut self) -> Canvas {
         // Same stuff as above.  Enter fullscreen; enter keypad mode; clear the
         // screen.
-        let tidy_cup = self.info.write_tidy_cap("smcup", "rmcup");
-        let tidy_kx = self.info.write_tidy_cap("smkx", "rmkx");
-        self.info.write_cap("clear");
+        let tidy_cup = self.info.write_tidy_cap(TerminalInfo::enter_ca_mode, TerminalInfo::exit_ca_mode);
+        let tidy_kx = self.info.write_tidy_cap(TerminalInfo::keypad_xmit, TerminalInfo::keypad_local);
+        self.info.clear_screen();
+        self.info.flush();
 
         // TODO intrflush, as above...?
 
-        let mut tidy_termstate = termios::TidyTerminalState(self.info.in_fd);
+        let mut tidy_termstate = self.info.checkpoint_termstate();
         tidy_termstate.cbreak();
 
         let mut canv = Canvas(&self.info, 0, 0, self.height(), self.width());
@@ -0,0 +1,599 @@
+/** Native reader for compiled terminfo databases.
+ *
+ * This does the job of ncurses' `setupterm`: find the compiled entry for a
+ * terminal, then pick it apart into plain Rust data.  No globals, no C
+ * strings, and no libncurses required.
+ *
+ * Both the legacy format (16-bit numbers) and the ncurses 6.1 format (32-bit
+ * numbers) are understood, as are the extended capabilities ncurses appends
+ * for user-defined names like `Tc` and `Smulx`.  See term(5) for the gory
+ * details.
+ */
+
+use std::cmp::max;
+use std::collections::HashMap;
+use std::io;
+use std::io::File;
+use std::io::fs::PathExtensions;
+use std::os;
+
+use capnames::{BOOLNAMES,NUMNAMES,STRNAMES};
+
+// Magic numbers at the start of a compiled entry.
+static MAGIC_LEGACY: i16 = 0o432;
+static MAGIC_32BIT: i16 = 0o1036;
+
+// Where ncurses looks when nothing in the environment says otherwise.
+// (ncurses bakes its own list in at compile time, but it's always some subset
+// of these.)
+static SYSTEM_DIRS: &'static [&'static str] = &[
+    "/etc/terminfo",
+    "/lib/terminfo",
+    "/usr/share/terminfo",
+    "/usr/lib/terminfo",
+    "/usr/share/lib/terminfo",
+];
+
+
+#[derive(Show)]
+pub enum TermInfoError {
+    /** None of the terminfo directories exist at all. */
+    NoDatabase,
+    /** There's a database, but it has no entry for this terminal. */
+    NotFound(String),
+    /** Found a file, but it isn't a compiled terminfo entry we understand. */
+    BadFormat(&'static str),
+    /** Found a file, but couldn't read it. */
+    Io(io::IoError),
+}
+
+
+/** The three kinds of capability terminfo knows about. */
+#[derive(Clone, Copy, PartialEq, Show)]
+pub enum CapabilityType {
+    Flag,
+    Number,
+    String,
+}
+
+
+/** A single terminal's capabilities, as read from the terminfo database. */
+pub struct TermInfo {
+    /** All the names this terminal goes by.  The last one is usually a
+     * human-readable description. */
+    pub names: Vec<String>,
+
+    flags: HashMap<String, bool>,
+    numbers: HashMap<String, u32>,
+    strings: HashMap<String, Vec<u8>>,
+    /** Types of the extended capabilities, which aren't in the standard
+     * name tables. */
+    extended: HashMap<String, CapabilityType>,
+}
+
+impl TermInfo {
+    /** Finds and loads the entry for the named terminal.
+     *
+     * Searches `$TERMINFO`, then `~/.terminfo`, then `$TERMINFO_DIRS`, then
+     * the usual system directories -- the same order ncurses uses.
+     */
+    pub fn load(term: &str) -> Result<TermInfo, TermInfoError> {
+        // A slash would let TERM point anywhere on disk, which is not what
+        // anyone means by it
+        if term.is_empty() || term.contains_char('/') {
+            return Err(TermInfoError::NotFound(term.to_string()));
+        }
+
+        let first = term.char_at(0);
+        let mut found_database = false;
+        for dir in search_dirs().iter() {
+            if ! dir.is_dir() {
+                continue;
+            }
+            found_database = true;
+
+            // Entries are filed under their first letter, except on OS X,
+            // which uses the letter's hex code instead
+            let candidates = [
+                dir.join(first.to_string()).join(term),
+                dir.join(format!("{:x}", first as u32)).join(term),
+            ];
+            for path in candidates.iter() {
+                if path.is_file() {
+                    return TermInfo::load_path(path);
+                }
+            }
+        }
+
+        if found_database {
+            return Err(TermInfoError::NotFound(term.to_string()));
+        }
+        else {
+            return Err(TermInfoError::NoDatabase);
+        }
+    }
+
+    /** Loads a compiled terminfo entry from a specific file. */
+    pub fn load_path(path: &Path) -> Result<TermInfo, TermInfoError> {
+        let data = match File::open(path).and_then(|mut f| f.read_to_end()) {
+            Ok(data) => data,
+            Err(err) => return Err(TermInfoError::Io(err)),
+        };
+
+        return TermInfo::parse(data.as_slice());
+    }
+
+    /** Parses a compiled terminfo entry. */
+    pub fn parse(data: &[u8]) -> Result<TermInfo, TermInfoError> {
+        let mut reader = EntryReader{ data: data, pos: 0 };
+
+        // Header: six little-endian shorts
+        let magic = try!(reader.read_i16());
+        let number_size;
+        if magic == MAGIC_LEGACY {
+            number_size = 2;
+        }
+        else if magic == MAGIC_32BIT {
+            number_size = 4;
+        }
+        else {
+            return Err(TermInfoError::BadFormat("bad magic number"));
+        }
+
+        let names_size = try!(reader.read_count());
+        let flag_count = try!(reader.read_count());
+        let number_count = try!(reader.read_count());
+        let string_count = try!(reader.read_count());
+        let string_table_size = try!(reader.read_count());
+
+        // Names, separated by | and terminated by a NUL
+        let names_bytes = try!(reader.read_bytes(names_size));
+        let names_end = names_bytes.iter().position(|&b| b == 0).unwrap_or(names_size);
+        let names = String::from_utf8_lossy(names_bytes.slice_to(names_end))
+            .as_slice().split('|').map(|name| name.to_string()).collect();
+
+        // Flags, one byte apiece.  Anything besides 1 means absent or
+        // cancelled.  Note that a newer ncurses may know about capabilities
+        // we don't; those are skipped.
+        let mut flags = HashMap::new();
+        for i in range(0, flag_count) {
+            let value = try!(reader.read_u8());
+            if value == 1 && i < BOOLNAMES.len() {
+                flags.insert(BOOLNAMES[i].to_string(), true);
+            }
+        }
+
+        // Numbers start on an even byte
+        reader.align();
+
+        // Numbers.  Negative means absent or cancelled
+        let mut numbers = HashMap::new();
+        for i in range(0, number_count) {
+            let value = try!(reader.read_number(number_size));
+            if value >= 0 && i < NUMNAMES.len() {
+                numbers.insert(NUMNAMES[i].to_string(), value as u32);
+            }
+        }
+
+        // Strings: first a list of offsets, then the table they point into
+        let mut offsets = Vec::with_capacity(string_count);
+        for _ in range(0, string_count) {
+            offsets.push(try!(reader.read_i16()));
+        }
+        let string_table = try!(reader.read_bytes(string_table_size));
+
+        let mut strings = HashMap::new();
+        for (i, &offset) in offsets.iter().enumerate() {
+            if offset < 0 || i >= STRNAMES.len() {
+                continue;
+            }
+            let value = try!(read_cstr(string_table, offset as usize));
+            strings.insert(STRNAMES[i].to_string(), value.to_vec());
+        }
+
+        let mut terminfo = TermInfo{
+            names: names,
+            flags: flags,
+            numbers: numbers,
+            strings: strings,
+            extended: HashMap::new(),
+        };
+
+        // Anything left over is the extended section
+        reader.align();
+        if ! reader.is_at_end() {
+            try!(terminfo.read_extended(&mut reader, number_size));
+        }
+
+        return Ok(terminfo);
+    }
+
+    /** Reads the extended capabilities.  These look much like the standard
+     * ones, except that the names are spelled out too: the string table holds
+     * all the string values, followed by all the names, flags first. */
+    fn read_extended(&mut self, reader: &mut EntryReader, number_size: usize) -> Result<(), TermInfoError> {
+        let flag_count = try!(reader.read_count());
+        let number_count = try!(reader.read_count());
+        let string_count = try!(reader.read_count());
+        // Number of offsets into the string table, i.e. values plus names;
+        // redundant with the above
+        try!(reader.read_count());
+        let string_table_size = try!(reader.read_count());
+
+        let mut flag_values = Vec::with_capacity(flag_count);
+        for _ in range(0, flag_count) {
+            flag_values.push(try!(reader.read_u8()));
+        }
+        reader.align();
+
+        let mut number_values = Vec::with_capacity(number_count);
+        for _ in range(0, number_count) {
+            number_values.push(try!(reader.read_number(number_size)));
+        }
+
+        let mut string_offsets = Vec::with_capacity(string_count);
+        for _ in range(0, string_count) {
+            string_offsets.push(try!(reader.read_i16()));
+        }
+        let mut name_offsets = Vec::with_capacity(flag_count + number_count + string_count);
+        for _ in range(0, flag_count + number_count + string_count) {
+            name_offsets.push(try!(reader.read_i16()));
+        }
+        let string_table = try!(reader.read_bytes(string_table_size));
+
+        // The names start right after the last value.  Absent strings don't
+        // take up any room
+        let mut string_values = Vec::with_capacity(string_count);
+        let mut names_start = 0;
+        for &offset in string_offsets.iter() {
+            if offset < 0 {
+                string_values.push(None);
+                continue;
+            }
+            let value = try!(read_cstr(string_table, offset as usize));
+            names_start = max(names_start, offset as usize + value.len() + 1);
+            string_values.push(Some(value));
+        }
+        if names_start > string_table.len() {
+            return Err(TermInfoError::BadFormat("extended names out of range"));
+        }
+        let name_table = string_table.slice_from(names_start);
+
+        let mut names = Vec::with_capacity(name_offsets.len());
+        for &offset in name_offsets.iter() {
+            if offset < 0 {
+                return Err(TermInfoError::BadFormat("missing extended capability name"));
+            }
+            let name = try!(read_cstr(name_table, offset as usize));
+            names.push(String::from_utf8_lossy(name).into_owned());
+        }
+
+        let (flag_names, rest) = names.split_at(flag_count);
+        let (number_names, string_names) = rest.split_at(number_count);
+
+        for (name, &value) in flag_names.iter().zip(flag_values.iter()) {
+            self.extended.insert(name.clone(), CapabilityType::Flag);
+            if value == 1 {
+                self.flags.insert(name.clone(), true);
+            }
+        }
+        for (name, &value) in number_names.iter().zip(number_values.iter()) {
+            self.extended.insert(name.clone(), CapabilityType::Number);
+            if value >= 0 {
+                self.numbers.insert(name.clone(), value as u32);
+            }
+        }
+        for (name, value) in string_names.iter().zip(string_values.iter()) {
+            self.extended.insert(name.clone(), CapabilityType::String);
+            match *value {
+                Some(value) => { self.strings.insert(name.clone(), value.to_vec()); }
+                None => (),
+            }
+        }
+
+        return Ok(());
+    }
+
+
+    // ------------------------------------------------------------------------
+    // Lookup
+
+    /** Returns what kind of capability `name` is, or None if it's not a
+     * capability at all.  Extended capabilities only count if this terminal
+     * defines them. */
+    pub fn capability_type(&self, name: &str) -> Option<CapabilityType> {
+        if BOOLNAMES.contains(&name) {
+            return Some(CapabilityType::Flag);
+        }
+        else if NUMNAMES.contains(&name) {
+            return Some(CapabilityType::Number);
+        }
+        else if STRNAMES.contains(&name) {
+            return Some(CapabilityType::String);
+        }
+        return self.extended.get(name).map(|&cap_type| cap_type);
+    }
+
+    /** Returns a boolean capability.  Absent flags are simply false; None
+     * means `name` isn't a boolean capability in the first place. */
+    pub fn flag(&self, name: &str) -> Option<bool> {
+        if self.capability_type(name) != Some(CapabilityType::Flag) {
+            return None;
+        }
+        return Some(self.flags.get(name).map(|&value| value).unwrap_or(false));
+    }
+
+    /** Returns a numeric capability, or None if the terminal doesn't have it. */
+    pub fn number(&self, name: &str) -> Option<u32> {
+        return self.numbers.get(name).map(|&value| value);
+    }
+
+    /** Returns a string capability, unformatted, or None if the terminal
+     * doesn't have it. */
+    pub fn string(&self, name: &str) -> Option<&[u8]> {
+        return self.strings.get(name).map(|value| value.as_slice());
+    }
+
+    /** Returns the names of all the extended capabilities this terminal
+     * defines, whether or not it actually has them. */
+    pub fn extended_names(&self) -> Vec<&str> {
+        return self.extended.keys().map(|name| name.as_slice()).collect();
+    }
+
+    /** Returns the names of all the string capabilities this terminal has. */
+    pub fn string_names(&self) -> Vec<&str> {
+        return self.strings.keys().map(|name| name.as_slice()).collect();
+    }
+}
+
+
+/** Returns the directories to search for terminfo entries, in order. */
+fn search_dirs() -> Vec<Path> {
+    let mut dirs = vec![];
+
+    match os::getenv("TERMINFO") {
+        Some(dir) => dirs.push(Path::new(dir)),
+        None => (),
+    }
+
+    match os::homedir() {
+        Some(home) => dirs.push(home.join(".terminfo")),
+        None => (),
+    }
+
+    match os::getenv("TERMINFO_DIRS") {
+        Some(dirlist) => {
+            for dir in dirlist.as_slice().split(':') {
+                // An empty entry means "the system directories go here"
+                if dir.is_empty() {
+                    dirs.extend(SYSTEM_DIRS.iter().map(|dir| Path::new(*dir)));
+                }
+                else {
+                    dirs.push(Path::new(dir));
+                }
+            }
+        }
+        None => (),
+    }
+
+    dirs.extend(SYSTEM_DIRS.iter().map(|dir| Path::new(*dir)));
+    return dirs;
+}
+
+/** Returns the NUL-terminated string starting at `offset`. */
+fn read_cstr(table: &[u8], offset: usize) -> Result<&[u8], TermInfoError> {
+    if offset > table.len() {
+        return Err(TermInfoError::BadFormat("string offset out of range"));
+    }
+    let rest = table.slice_from(offset);
+    return match rest.iter().position(|&b| b == 0) {
+        Some(end) => Ok(rest.slice_to(end)),
+        None => Err(TermInfoError::BadFormat("unterminated string")),
+    };
+}
+
+
+/** Walks through the bytes of a compiled entry.  Everything is little-endian. */
+struct EntryReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> EntryReader<'a> {
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], TermInfoError> {
+        if self.pos + count > self.data.len() {
+            return Err(TermInfoError::BadFormat("entry is truncated"));
+        }
+        let bytes = self.data.slice(self.pos, self.pos + count);
+        self.pos += count;
+        return Ok(bytes);
+    }
+
+    fn read_u8(&mut self) -> Result<u8, TermInfoError> {
+        let bytes = try!(self.read_bytes(1));
+        return Ok(bytes[0]);
+    }
+
+    fn read_i16(&mut self) -> Result<i16, TermInfoError> {
+        let bytes = try!(self.read_bytes(2));
+        return Ok((bytes[0] as u16 | ((bytes[1] as u16) << 8)) as i16);
+    }
+
+    fn read_i32(&mut self) -> Result<i32, TermInfoError> {
+        let bytes = try!(self.read_bytes(4));
+        return Ok((
+            bytes[0] as u32
+            | ((bytes[1] as u32) << 8)
+            | ((bytes[2] as u32) << 16)
+            | ((bytes[3] as u32) << 24)
+        ) as i32);
+    }
+
+    /** Reads a number in whichever width this entry uses. */
+    fn read_number(&mut self, size: usize) -> Result<i32, TermInfoError> {
+        if size == 4 {
+            return self.read_i32();
+        }
+        else {
+            return self.read_i16().map(|n| n as i32);
+        }
+    }
+
+    /** Reads a header count, which had better not be negative. */
+    fn read_count(&mut self) -> Result<usize, TermInfoError> {
+        let count = try!(self.read_i16());
+        if count < 0 {
+            return Err(TermInfoError::BadFormat("negative count in header"));
+        }
+        return Ok(count as usize);
+    }
+
+    fn is_at_end(&self) -> bool {
+        return self.pos >= self.data.len();
+    }
+
+    /** Skips a byte, if necessary, to land on an even offset. */
+    fn align(&mut self) {
+        if self.pos % 2 == 1 {
+            self.pos += 1;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{MAGIC_LEGACY,MAGIC_32BIT,CapabilityType,TermInfo};
+
+    fn push_i16(out: &mut Vec<u8>, n: i16) {
+        out.push(n as u8);
+        out.push((n >> 8) as u8);
+    }
+
+    fn push_i32(out: &mut Vec<u8>, n: i32) {
+        push_i16(out, n as i16);
+        push_i16(out, (n >> 16) as i16);
+    }
+
+    /** Builds a compiled entry by hand, the way tic would lay it out. */
+    fn entry(magic: i16, flags: &[u8], numbers: &[i32], offsets: &[i16], table: &[u8]) -> Vec<u8> {
+        let names = b"test|Test terminal\0";
+        let mut out = vec![];
+        push_i16(&mut out, magic);
+        push_i16(&mut out, names.len() as i16);
+        push_i16(&mut out, flags.len() as i16);
+        push_i16(&mut out, numbers.len() as i16);
+        push_i16(&mut out, offsets.len() as i16);
+        push_i16(&mut out, table.len() as i16);
+        out.push_all(names);
+        out.push_all(flags);
+        if out.len() % 2 == 1 {
+            out.push(0);
+        }
+        for &n in numbers.iter() {
+            if magic == MAGIC_32BIT {
+                push_i32(&mut out, n);
+            }
+            else {
+                push_i16(&mut out, n as i16);
+            }
+        }
+        for &offset in offsets.iter() {
+            push_i16(&mut out, offset);
+        }
+        out.push_all(table);
+        return out;
+    }
+
+    #[test]
+    fn legacy_entry() {
+        // The names and two flags add up to an odd length, so there's a
+        // padding byte before the numbers
+        let data = entry(MAGIC_LEGACY, &[0, 1], &[80, -1, 24], &[-1, 0, 2], b"\x07\0\r\0");
+        let info = TermInfo::parse(data.as_slice()).ok().expect("entry should parse");
+
+        assert_eq!(info.names, vec!["test".to_string(), "Test terminal".to_string()]);
+        assert_eq!(info.flag("bw"), Some(false));
+        assert_eq!(info.flag("am"), Some(true));
+        assert_eq!(info.number("cols"), Some(80));
+        assert_eq!(info.number("it"), None);
+        assert_eq!(info.number("lines"), Some(24));
+        assert_eq!(info.string("cbt"), None);
+        assert_eq!(info.string("bel").map(|s| s.to_vec()), Some(vec![7u8]));
+        assert_eq!(info.string("cr").map(|s| s.to_vec()), Some(vec![13u8]));
+    }
+
+    #[test]
+    fn entry_with_32bit_numbers() {
+        // colors is the fourteenth number, and too big for 16 bits
+        let mut numbers = vec![-1; 14];
+        numbers[0] = 80;
+        numbers[13] = 1 << 24;
+        let data = entry(MAGIC_32BIT, &[1], numbers.as_slice(), &[], b"");
+        let info = TermInfo::parse(data.as_slice()).ok().expect("entry should parse");
+
+        assert_eq!(info.flag("bw"), Some(true));
+        assert_eq!(info.number("cols"), Some(80));
+        assert_eq!(info.number("lines"), None);
+        assert_eq!(info.number("colors"), Some(1 << 24));
+    }
+
+    #[test]
+    fn extended_entry() {
+        // The standard string table is an odd length, so the extended
+        // section starts after a padding byte
+        let mut data = entry(MAGIC_LEGACY, &[1], &[80], &[0, 4, 6, 8],
+            b"\x1b[Z\0\x07\0\r\0\x1b[%i%p1%d;%p2%dr\0");
+        if data.len() % 2 == 1 {
+            data.push(0);
+        }
+
+        // Se comes first in the table but Ss ends last, so the names have to
+        // start after Ss, not after whichever string was listed last
+        let values = b"\x1b[2 q\0\x1b[%p1%d q\0";
+        let names = b"XT\0U8\0Ss\0Se\0Setulc\0";
+        push_i16(&mut data, 1);
+        push_i16(&mut data, 1);
+        push_i16(&mut data, 3);
+        push_i16(&mut data, 8);
+        push_i16(&mut data, (values.len() + names.len()) as i16);
+        // One flag leaves the numbers on an odd byte
+        data.push(1);
+        data.push(0);
+        push_i16(&mut data, 1);
+        for &offset in [6, 0, -1, 0, 3, 6, 9, 12].iter() {
+            push_i16(&mut data, offset);
+        }
+        data.push_all(values);
+        data.push_all(names);
+
+        let info = TermInfo::parse(data.as_slice()).ok().expect("entry should parse");
+
+        assert_eq!(info.flag("bw"), Some(true));
+        assert_eq!(info.number("cols"), Some(80));
+        assert_eq!(info.string("csr").map(|s| s.to_vec()), Some(b"\x1b[%i%p1%d;%p2%dr".to_vec()));
+
+        assert_eq!(info.flag("XT"), Some(true));
+        assert_eq!(info.flag("Tc"), None);
+        assert_eq!(info.number("U8"), Some(1));
+        assert_eq!(info.string("Ss").map(|s| s.to_vec()), Some(b"\x1b[%p1%d q".to_vec()));
+        assert_eq!(info.string("Se").map(|s| s.to_vec()), Some(b"\x1b[2 q".to_vec()));
+        assert_eq!(info.string("Setulc"), None);
+        assert_eq!(info.capability_type("Setulc"), Some(CapabilityType::String));
+        assert_eq!(info.extended_names().len(), 5);
+    }
+
+    #[test]
+    fn bad_entries() {
+        let data = entry(0o1234, &[], &[], &[], b"");
+        assert!(TermInfo::parse(data.as_slice()).is_err());
+
+        let data = entry(MAGIC_LEGACY, &[1], &[80], &[0], b"\x07\0");
+        // Cut off partway through the string table
+        assert!(TermInfo::parse(data.slice_to(data.len() - 1)).is_err());
+
+        // A string offset past the end of the table
+        let data = entry(MAGIC_LEGACY, &[], &[], &[5], b"\x07\0");
+        assert!(TermInfo::parse(data.as_slice()).is_err());
+    }
+}
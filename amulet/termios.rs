@@ -1,6 +1,11 @@
 use std::clone::Clone;
-use libc::c_int;
+use libc;
+use libc::{c_int,c_short,c_ulong,c_void,size_t,ssize_t};
+use std::io::{IoError,IoResult};
+use std::os;
 use std::rc::Rc;
+use std::sync::{Once,ONCE_INIT};
+use std::sync::atomic::{AtomicIsize,AtomicUsize,ATOMIC_ISIZE_INIT,ATOMIC_USIZE_INIT,Ordering};
 
 // -----------------------------------------------------------------------------
 // Platform-specific implementations
@@ -13,7 +18,7 @@ use std::rc::Rc;
 
 #[cfg(target_os="linux")]
 pub mod imp {
-    use libc::{c_int,c_uint,c_ushort,c_void};
+    use libc::{c_int,c_short,c_uint,c_ulong,c_ushort,c_void};
 
     static NCCS: c_int = 32;
     pub type cc_t = c_int;
@@ -112,6 +117,28 @@ pub mod imp {
     pub static  B3500000: c_uint = 0x0100e;
     pub static  B4000000: c_uint = 0x0100f;
 
+    /** Converts one of the B* speed constants to bits per second. */
+    pub fn speed_to_baud_rate(speed: speed_t) -> usize {
+        // Lovingly hand-translated from the above
+        let rates = [
+            (B0, 0), (B50, 50), (B75, 75), (B110, 110), (B134, 134),
+            (B150, 150), (B200, 200), (B300, 300), (B600, 600),
+            (B1200, 1200), (B1800, 1800), (B2400, 2400), (B4800, 4800),
+            (B9600, 9600), (B19200, 19200), (B38400, 38400),
+            (B57600, 57600), (B115200, 115200), (B230400, 230400),
+            (B460800, 460800), (B500000, 500000), (B576000, 576000),
+            (B921600, 921600), (B1000000, 1000000), (B1152000, 1152000),
+            (B1500000, 1500000), (B2000000, 2000000), (B2500000, 2500000),
+            (B3000000, 3000000), (B3500000, 3500000), (B4000000, 4000000),
+        ];
+        for &(constant, rate) in rates.iter() {
+            if constant == speed {
+                return rate;
+            }
+        }
+        return 0;
+    }
+
     /* c_lflag bits */
     pub static ISIG:     c_uint = 0x00001;
     pub static ICANON:   c_uint = 0x00002;
@@ -131,6 +158,50 @@ pub mod imp {
     /* ioctls */
     pub static TIOCGWINSZ: c_int = 0x5413;
 
+    /* signals */
+    pub static SIGCONT: c_int = 18;
+    pub static SIGWINCH: c_int = 28;
+    pub static SA_RESTART: c_int = 0x10000000;
+
+    /* poll events */
+    pub static POLLIN: c_short = 0x001;
+
+    /* fcntl */
+    pub static F_GETFL: c_int = 3;
+    pub static F_SETFL: c_int = 4;
+    pub static O_NONBLOCK: c_int = 0o4000;
+
+
+    pub struct sigaction {
+        // Really a function pointer, but it can also be SIG_DFL or SIG_IGN,
+        // which are 0 and 1
+        pub sa_handler: usize,
+        // 1024 bits' worth, per glibc
+        pub sa_mask: [c_ulong; 16],
+        pub sa_flags: c_int,
+        sa_restorer: *mut c_void,
+    }
+
+    // Same deal as blank_termios below
+    pub fn blank_sigaction() -> sigaction {
+        return sigaction{
+            sa_handler: 0,
+            sa_mask: [0; 16],
+            sa_flags: 0,
+            sa_restorer: 0 as *mut c_void,
+        };
+    }
+
+    // SA_RESTART keeps the signal from breaking reads and writes elsewhere in
+    // the program; waiting for input doesn't rely on being interrupted, since
+    // the handlers also write to a pipe
+    pub fn new_sigaction(handler: extern "C" fn(c_int)) -> sigaction {
+        let mut action = blank_sigaction();
+        action.sa_handler = handler as usize;
+        action.sa_flags = SA_RESTART;
+        return action;
+    }
+
 
     pub struct termios {
         pub c_iflag: tcflag_t,      // input modes
@@ -184,15 +255,18 @@ pub mod imp {
         fn ioctl_p(fd: c_int, request: c_int, arg1: *mut c_void) -> c_int;
     }
 
+    /** Asks the kernel how big the terminal is, as (rows, columns).  Returns
+     * None if it doesn't know, which happens on e.g. serial consoles. */
     #[fixed_stack_segment]
-    pub fn request_terminal_size(fd: c_int) -> (usize, usize) {
+    pub fn request_terminal_size(fd: c_int) -> Option<(usize, usize)> {
         let mut size = winsize{ ws_row: 0, ws_col: 0, ws_xpixel: 0, ws_ypixel: 0 };
 
         let res = unsafe { ioctl_p(fd, TIOCGWINSZ, &mut size as *mut _ as *mut c_void) };
 
-        // XXX return value is -1 on failure
-        // returns width, height
-        return (size.ws_col as usize, size.ws_row as usize);
+        if res < 0 || size.ws_row == 0 || size.ws_col == 0 {
+            return None;
+        }
+        return Some((size.ws_row as usize, size.ws_col as usize));
     }
 }
 
@@ -202,8 +276,181 @@ pub mod imp {
 extern {
     fn tcgetattr(fd: c_int, termios_p: *mut imp::termios) -> c_int;
     fn tcsetattr(fd: c_int, optional_actions: c_int, termios_p: *const imp::termios) -> c_int;
+    fn cfgetospeed(termios_p: *const imp::termios) -> imp::speed_t;
+    fn isatty(fd: c_int) -> c_int;
+    fn sigaction(signum: c_int, act: *const imp::sigaction, oldact: *mut imp::sigaction) -> c_int;
+    fn poll(fds: *mut pollfd, nfds: c_ulong, timeout: c_int) -> c_int;
+    fn pipe(fds: *mut c_int) -> c_int;
+    fn fcntl(fd: c_int, cmd: c_int, arg: c_int) -> c_int;
+    fn read(fd: c_int, buf: *mut c_void, count: size_t) -> ssize_t;
+    fn write(fd: c_int, buf: *const c_void, count: size_t) -> ssize_t;
+}
+
+struct pollfd {
+    fd: c_int,
+    events: c_short,
+    revents: c_short,
+}
+
+/** Returns whether an fd refers to a terminal. */
+pub fn is_tty(fd: c_int) -> bool {
+    return unsafe { isatty(fd) } == 1;
+}
+
+/** Returns the output speed of a tty, in bits per second, or zero if it's not
+  * a tty or the speed is something weird.
+  */
+pub fn output_baud_rate(fd: c_int) -> usize {
+    let mut c_termios = imp::blank_termios();
+
+    let speed = unsafe {
+        if tcgetattr(fd, &mut c_termios) != 0 {
+            return 0;
+        }
+        cfgetospeed(&c_termios)
+    };
+
+    return imp::speed_to_baud_rate(speed);
+}
+
+/** Returns whether a tty turns \n into \r\n on output.  False if it's not a
+  * tty, or there's otherwise no telling.
+  */
+pub fn translates_newlines(fd: c_int) -> bool {
+    let mut c_termios = imp::blank_termios();
+
+    if unsafe { tcgetattr(fd, &mut c_termios) } != 0 {
+        return false;
+    }
+
+    let flags = imp::OPOST | imp::ONLCR;
+    return c_termios.c_oflag & flags == flags;
+}
+
+// -----------------------------------------------------------------------------
+// Signals
+
+// Signal handlers can't do much of anything, so ours only bump a counter and
+// then poke a pipe.  Everyone else compares the counter to the last value they
+// saw; the pipe is there so wait_for_input() can wake up for a signal that
+// arrives just before it starts waiting.
+static SIGNAL_PIPE_READ: AtomicIsize = ATOMIC_ISIZE_INIT;
+static SIGNAL_PIPE_WRITE: AtomicIsize = ATOMIC_ISIZE_INIT;
+static OPEN_SIGNAL_PIPE: Once = ONCE_INIT;
+
+/** Creates the pipe the signal handlers write to.  Both ends are nonblocking,
+  * so a handler never gets stuck on a full pipe, and draining it never waits.
+  * If the pipe can't be made, both ends stay at -1 and nothing writes to it.
+  */
+#[fixed_stack_segment]
+fn open_signal_pipe() {
+    OPEN_SIGNAL_PIPE.call_once(|| {
+        SIGNAL_PIPE_READ.store(-1, Ordering::SeqCst);
+        SIGNAL_PIPE_WRITE.store(-1, Ordering::SeqCst);
+
+        let mut fds = [0 as c_int; 2];
+        if unsafe { pipe(fds.as_mut_ptr()) } != 0 {
+            return;
+        }
+        for &fd in fds.iter() {
+            unsafe {
+                let flags = fcntl(fd, imp::F_GETFL, 0);
+                fcntl(fd, imp::F_SETFL, flags | imp::O_NONBLOCK);
+            }
+        }
+        SIGNAL_PIPE_READ.store(fds[0] as isize, Ordering::SeqCst);
+        SIGNAL_PIPE_WRITE.store(fds[1] as isize, Ordering::SeqCst);
+    });
+}
+
+#[fixed_stack_segment]
+fn poke_signal_pipe() {
+    let fd = SIGNAL_PIPE_WRITE.load(Ordering::SeqCst);
+    if fd < 0 {
+        return;
+    }
+    // If the pipe is full, there's already a wakeup waiting, which is fine
+    let byte = 0u8;
+    unsafe {
+        write(fd as c_int, &byte as *const u8 as *const c_void, 1);
+    }
 }
 
+#[fixed_stack_segment]
+fn drain_signal_pipe() {
+    let fd = SIGNAL_PIPE_READ.load(Ordering::SeqCst);
+    if fd < 0 {
+        return;
+    }
+    let mut buf = [0u8; 64];
+    loop {
+        let res = unsafe { read(fd as c_int, buf.as_mut_ptr() as *mut c_void, buf.len() as size_t) };
+        if res <= 0 {
+            break;
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Resizing
+
+// Bumped every time a SIGWINCH arrives.
+static RESIZE_GENERATION: AtomicUsize = ATOMIC_USIZE_INIT;
+
+extern "C" fn handle_sigwinch(_signum: c_int) {
+    RESIZE_GENERATION.fetch_add(1, Ordering::SeqCst);
+    poke_signal_pipe();
+}
+
+/** Returns a number that changes whenever the terminal is resized. */
+pub fn resize_generation() -> usize {
+    return RESIZE_GENERATION.load(Ordering::SeqCst);
+}
+
+// -----------------------------------------------------------------------------
+// Resuming
+
+// Same idea, for SIGCONT, which arrives when a stopped process (e.g. one
+// suspended with ^Z) is started again.  Whatever was on the screen is long
+// gone by then.
+static RESUME_GENERATION: AtomicUsize = ATOMIC_USIZE_INIT;
+
+extern "C" fn handle_sigcont(_signum: c_int) {
+    RESUME_GENERATION.fetch_add(1, Ordering::SeqCst);
+    poke_signal_pipe();
+}
+
+/** Returns a number that changes whenever the process is resumed after being
+  * stopped. */
+pub fn resume_generation() -> usize {
+    return RESUME_GENERATION.load(Ordering::SeqCst);
+}
+
+/** Blocks until there's something to read on the given fd.  Returns false if
+  * a signal (like SIGWINCH) came along first, or had already come along since
+  * the last call.
+  */
+#[fixed_stack_segment]
+pub fn wait_for_input(fd: c_int) -> bool {
+    let signal_fd = SIGNAL_PIPE_READ.load(Ordering::SeqCst) as c_int;
+    let mut fds = [
+        pollfd{ fd: fd, events: imp::POLLIN, revents: 0 },
+        // poll() ignores negative fds, so this is harmless without a pipe
+        pollfd{ fd: signal_fd, events: imp::POLLIN, revents: 0 },
+    ];
+    let res = unsafe { poll(fds.as_mut_ptr(), 2, -1) };
+    if res < 0 {
+        // Errors other than EINTR are left for the following read to discover
+        return os::errno() as c_int != libc::EINTR;
+    }
+    if fds[1].revents != 0 {
+        drain_signal_pipe();
+        return false;
+    }
+    return true;
+}
+
+
 /** Self-reverting access to termios state changes.
   *
   * When this object goes out of scope, it will restore the tty to whatever
@@ -214,32 +461,58 @@ pub struct TidyTerminalState {
     c_fd: c_int,
     c_termios_orig: imp::termios,
     c_termios_cur: imp::termios,
+    /** Signal handlers we replaced, to be put back when this goes away. */
+    previous_handlers: Vec<(c_int, imp::sigaction)>,
 }
 
 impl Drop for TidyTerminalState {
+    #[fixed_stack_segment]
     fn drop(&mut self) {
         self.restore_term();
+
+        // Newest first, in case the same signal was replaced twice
+        while let Some((signum, action)) = self.previous_handlers.pop() {
+            unsafe {
+                sigaction(signum, &action, 0 as *mut imp::sigaction);
+            }
+        }
     }
 }
 
+/** Captures the current settings of a tty.  Fails if the fd isn't a tty, or
+  * the kernel otherwise won't tell us its settings.
+  */
 #[fixed_stack_segment]
-pub fn TidyTerminalState(fd: c_int) -> TidyTerminalState {
+pub fn TidyTerminalState(fd: c_int) -> IoResult<TidyTerminalState> {
     let mut c_termios = imp::blank_termios();
 
-    // TODO this has a retval, but...  eh...
-    unsafe {
-        tcgetattr(fd as c_int, &mut c_termios);
+    let res = unsafe { tcgetattr(fd as c_int, &mut c_termios) };
+    if res != 0 {
+        return Err(IoError::last_error());
     }
 
-    return TidyTerminalState{
+    return Ok(TidyTerminalState{
         c_fd: fd as c_int,
         c_termios_cur: c_termios.clone(),
         c_termios_orig: c_termios,
-    };
+        previous_handlers: vec![],
+    });
 }
 
 // TODO: i want this impl only for ~T but that makes the drop not work
 impl TidyTerminalState {
+    /** Returns a new state object for the same tty, which will restore it to
+      * the settings it has *now* when it goes out of scope.
+      */
+    pub fn checkpoint(&self) -> TidyTerminalState {
+        return TidyTerminalState{
+            c_fd: self.c_fd,
+            c_termios_orig: self.c_termios_cur.clone(),
+            c_termios_cur: self.c_termios_cur.clone(),
+            previous_handlers: vec![],
+        };
+    }
+
     #[fixed_stack_segment]
     fn restore_term (&mut self) {
         unsafe {
@@ -253,6 +526,32 @@ impl TidyTerminalState {
         self.c_termios_cur = self.c_termios_orig.clone();
     }
 
+    #[fixed_stack_segment]
+    fn install_handler(&mut self, signum: c_int, handler: extern "C" fn(c_int)) {
+        open_signal_pipe();
+
+        let action = imp::new_sigaction(handler);
+        let mut previous = imp::blank_sigaction();
+        let res = unsafe { sigaction(signum, &action, &mut previous) };
+        if res == 0 {
+            self.previous_handlers.push((signum, previous));
+        }
+    }
+
+    /** Starts listening for SIGWINCH.  Whatever handler was there before is
+      * put back when this state goes out of scope.
+      */
+    pub fn install_resize_handler(&mut self) {
+        self.install_handler(imp::SIGWINCH, handle_sigwinch);
+    }
+
+    /** Starts listening for SIGCONT, with the same cleanup as
+      * install_resize_handler().
+      */
+    pub fn install_resume_handler(&mut self) {
+        self.install_handler(imp::SIGCONT, handle_sigcont);
+    }
+
 
     // --------------------------------------------------------------------------
     // Raw and cbreak.  (There's no "cooked" because that is, presumably, the
@@ -0,0 +1,682 @@
+/** Themes: palettes read from a config file, so users can change colors
+ * without recompiling anything.
+ *
+ * Two formats are understood, a small subset of TOML and plain JSON, and
+ * both describe the same thing.  Each palette name gets a table of settings,
+ * plus optional tables that override them for particular color depths:
+ *
+ *     # TOML allows comments
+ *     [header]
+ *     fg = "white"
+ *     bg = "blue"
+ *     attrs = "bold"
+ *
+ *     [header.256]
+ *     bg = "#005f87"
+ *
+ *     [header.mono]
+ *     attrs = ["bold", "reverse"]
+ *
+ * or, the same thing in JSON:
+ *
+ *     {"header": {"fg": "white", "bg": "blue", "attrs": "bold",
+ *         "256": {"bg": "#005f87"}, "mono": {"attrs": ["bold", "reverse"]}}}
+ *
+ * The settings are `fg`, `bg`, `underline_color`, and `attrs`, or `style`
+ * for the whole thing in the form `Style::parse()` takes, e.g. `style = "bold
+ * white on blue"`; any other settings are applied on top.  Colors can be
+ * names (`red`, `bright_black`, `default`), `#rrggbb`, or palette indexes;
+ * attributes are the names `Style::with_attribute()` takes, either in a list
+ * or as a single string separated by commas or spaces.  The depths are
+ * `mono`, `16`, `256`, and `truecolor`.  An override starts from the main
+ * settings, except that its `attrs`, if any, replace the main ones.
+ */
+
+use std::char;
+use std::error::Error;
+use std::fmt;
+use std::io::{File,IoError};
+
+use color::{Color,ColorDepth};
+use ll::Style;
+use palette::Palette;
+
+#[derive(Show)]
+pub enum ThemeError {
+    /** Couldn't read the file at all. */
+    Io(IoError),
+    /** Something is wrong on the given line, counting from 1. */
+    Parse(usize, String),
+}
+
+impl fmt::String for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match *self {
+            ThemeError::Io(ref err) => write!(f, "couldn't read theme: {}", err),
+            ThemeError::Parse(line, ref message) => write!(f, "line {}: {}", line, message),
+        };
+    }
+}
+
+impl Error for ThemeError {
+    fn description(&self) -> &str {
+        return match *self {
+            ThemeError::Io(_) => "I/O error",
+            ThemeError::Parse(..) => "invalid theme",
+        };
+    }
+}
+
+
+/** Reads a theme from a file.  Files ending in `.json` are read as JSON;
+ * anything else, as TOML. */
+pub fn load(path: &Path) -> Result<Palette, ThemeError> {
+    let source = match File::open(path).and_then(|mut f| f.read_to_string()) {
+        Ok(source) => source,
+        Err(err) => return Err(ThemeError::Io(err)),
+    };
+
+    if path.extension_str() == Some("json") {
+        return parse_json(source.as_slice());
+    }
+    return parse_toml(source.as_slice());
+}
+
+pub fn parse_toml(source: &str) -> Result<Palette, ThemeError> {
+    let mut scanner = Scanner::new(source);
+    let root = try!(toml_document(&mut scanner));
+    return build_palette(root.as_slice());
+}
+
+pub fn parse_json(source: &str) -> Result<Palette, ThemeError> {
+    let mut scanner = Scanner::new(source);
+    scanner.skip_whitespace(false);
+    if scanner.peek() != Some('{') {
+        return scanner.error(format!("expected a JSON object, found {}", describe(scanner.peek())));
+    }
+    let root = try!(json_value(&mut scanner));
+    scanner.skip_whitespace(false);
+    if scanner.peek().is_some() {
+        return scanner.error(format!("expected end of file, found {}", describe(scanner.peek())));
+    }
+
+    return match root.value {
+        Value::Table(ref entries) => build_palette(entries.as_slice()),
+        _ => unreachable!(),
+    };
+}
+
+
+// -----------------------------------------------------------------------------
+// Turning a document into a palette
+
+static DEPTHS: [(&'static str, ColorDepth); 4] = [
+    ("mono", ColorDepth::Monochrome),
+    ("16", ColorDepth::Colors16),
+    ("256", ColorDepth::Colors256),
+    ("truecolor", ColorDepth::Truecolor),
+];
+
+fn depth_named(name: &str) -> Option<ColorDepth> {
+    for &(depth_name, depth) in DEPTHS.iter() {
+        if name == depth_name {
+            return Some(depth);
+        }
+    }
+    return None;
+}
+
+fn build_palette(root: &[(String, Node)]) -> Result<Palette, ThemeError> {
+    let mut palette = Palette::new();
+    for &(ref name, ref node) in root.iter() {
+        let settings = try!(expect_table(node, name.as_slice()));
+        let base = try!(apply_settings(Style(), settings, true));
+        palette.set(name.as_slice(), base.clone());
+
+        let mut has_mono = false;
+        let mut has_16 = false;
+        for &(ref key, ref node) in settings.iter() {
+            let depth = match depth_named(key.as_slice()) {
+                Some(depth) => depth,
+                None => continue,
+            };
+            let overrides = try!(expect_table(node, key.as_slice()));
+            let style = try!(apply_settings(base.clone(), overrides, false));
+            palette.set_for_depth(name.as_slice(), depth, style);
+
+            has_mono = has_mono || depth == ColorDepth::Monochrome;
+            has_16 = has_16 || depth == ColorDepth::Colors16;
+        }
+
+        // The main settings live in the monochrome slot, so they cover every
+        // depth; if that slot was overridden, they still need to cover the
+        // rest
+        if has_mono && ! has_16 {
+            palette.set_for_depth(name.as_slice(), ColorDepth::Colors16, base);
+        }
+    }
+    return Ok(palette);
+}
+
+fn apply_settings(style: Style, settings: &[(String, Node)], allow_depths: bool) -> Result<Style, ThemeError> {
+    let mut style = style;
+    // A whole style goes first, wherever it is, so the rest can modify it
+    for &(ref key, ref node) in settings.iter() {
+        if key.as_slice() == "style" {
+            style = try!(style_value(node));
+        }
+    }
+
+    for &(ref key, ref node) in settings.iter() {
+        match key.as_slice() {
+            "style" => (),
+            "fg" => style = style.fg(try!(color_value(node))),
+            "bg" => style = style.bg(try!(color_value(node))),
+            "underline_color" => style = style.underline_color(try!(color_value(node))),
+            "attrs" => {
+                style = without_attributes(&style);
+                for name in try!(attribute_names(node)).iter() {
+                    style = match style.with_attribute(name.as_slice()) {
+                        Some(style) => style,
+                        None => return Err(ThemeError::Parse(node.line, format!("unknown attribute {}", name))),
+                    };
+                }
+            }
+            _ if allow_depths && depth_named(key.as_slice()).is_some() => (),
+            _ => return Err(ThemeError::Parse(node.line, format!("unknown setting {}", key))),
+        }
+    }
+    return Ok(style);
+}
+
+/** Returns a copy of `style` with only the colors and link. */
+fn without_attributes(style: &Style) -> Style {
+    return Style{
+        fg_color: style.fg_color,
+        bg_color: style.bg_color,
+        underline_color: style.underline_color,
+        hyperlink: style.hyperlink.clone(),
+        ..Style()
+    };
+}
+
+fn expect_table<'a>(node: &'a Node, name: &str) -> Result<&'a [(String, Node)], ThemeError> {
+    return match node.value {
+        Value::Table(ref entries) => Ok(entries.as_slice()),
+        _ => Err(ThemeError::Parse(node.line, format!("{} should be a table", name))),
+    };
+}
+
+fn color_value(node: &Node) -> Result<Color, ThemeError> {
+    return match node.value {
+        Value::String(ref s) => match Color::parse(s.as_slice()) {
+            Some(color) => Ok(color),
+            None => Err(ThemeError::Parse(node.line, format!("unknown color {}", s))),
+        },
+        Value::Integer(n) if 0 <= n && n <= 255 => Ok(Color::Indexed(n as u8)),
+        Value::Integer(n) => Err(ThemeError::Parse(node.line, format!("color index {} isn't between 0 and 255", n))),
+        _ => Err(ThemeError::Parse(node.line, "colors should be strings or numbers".to_string())),
+    };
+}
+
+fn is_attribute_separator(ch: char) -> bool {
+    return ch == ',' || ch.is_whitespace();
+}
+
+fn style_value(node: &Node) -> Result<Style, ThemeError> {
+    return match node.value {
+        Value::String(ref s) => Style::parse(s.as_slice()).map_err(|err| ThemeError::Parse(node.line, format!("{}", err))),
+        _ => Err(ThemeError::Parse(node.line, "style should be a string".to_string())),
+    };
+}
+
+fn attribute_names(node: &Node) -> Result<Vec<String>, ThemeError> {
+    let mut names = vec![];
+    match node.value {
+        Value::String(ref s) => {
+            for name in s.as_slice().split(is_attribute_separator) {
+                if ! name.is_empty() {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        Value::Array(ref items) => {
+            for item in items.iter() {
+                match item.value {
+                    Value::String(ref s) => names.push(s.clone()),
+                    _ => return Err(ThemeError::Parse(item.line, "attributes should be strings".to_string())),
+                }
+            }
+        }
+        _ => return Err(ThemeError::Parse(node.line, "attrs should be a string or a list".to_string())),
+    }
+    return Ok(names);
+}
+
+
+// -----------------------------------------------------------------------------
+// Parsing
+
+/** A parsed value, without any idea yet what it means. */
+enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Node>),
+    /** Keys are kept in the order they appeared. */
+    Table(Vec<(String, Node)>),
+}
+
+/** A value, plus where it came from, for error messages. */
+struct Node {
+    line: usize,
+    value: Value,
+}
+
+fn describe(ch: Option<char>) -> String {
+    return match ch {
+        Some('\n') => "end of line".to_string(),
+        Some(ch) => format!("{:?}", ch),
+        None => "end of file".to_string(),
+    };
+}
+
+fn insert(table: &mut Vec<(String, Node)>, key: String, node: Node) -> Result<(), ThemeError> {
+    if table.iter().any(|&(ref existing, _)| *existing == key) {
+        return Err(ThemeError::Parse(node.line, format!("{} is set twice", key)));
+    }
+    table.push((key, node));
+    return Ok(());
+}
+
+/** Reads through a document one character at a time, keeping track of the
+ * line number. */
+struct Scanner {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Scanner {
+    fn new(source: &str) -> Scanner {
+        return Scanner{ chars: source.chars().collect(), pos: 0, line: 1 };
+    }
+
+    fn peek(&self) -> Option<char> {
+        if self.pos < self.chars.len() {
+            return Some(self.chars[self.pos]);
+        }
+        return None;
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek();
+        match ch {
+            Some(ch) => {
+                self.pos += 1;
+                if ch == '\n' {
+                    self.line += 1;
+                }
+            }
+            None => (),
+        }
+        return ch;
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ThemeError> {
+        if self.peek() == Some(expected) {
+            self.bump();
+            return Ok(());
+        }
+        return self.error(format!("expected {:?}, found {}", expected, describe(self.peek())));
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, ThemeError> {
+        return Err(ThemeError::Parse(self.line, message));
+    }
+
+    /** Skips spaces and tabs, but not newlines. */
+    fn skip_spaces(&mut self) {
+        loop {
+            match self.peek() {
+                Some(' ') | Some('\t') | Some('\r') => { self.bump(); }
+                _ => return,
+            }
+        }
+    }
+
+    /** Skips all whitespace, including newlines, plus `#` comments if
+     * `comments` is set. */
+    fn skip_whitespace(&mut self, comments: bool) {
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                Some('\n') => { self.bump(); }
+                Some('#') if comments => self.skip_comment(),
+                _ => return,
+            }
+        }
+    }
+
+    /** Skips a comment, if there is one, up to but not including the end of
+     * the line. */
+    fn skip_comment(&mut self) {
+        if self.peek() != Some('#') {
+            return;
+        }
+        loop {
+            match self.peek() {
+                Some('\n') | None => return,
+                _ => { self.bump(); }
+            }
+        }
+    }
+
+    /** Reads a quoted string, starting at the opening quote.  Escapes are
+     * the same in JSON and TOML, except that TOML has single-quoted strings
+     * with no escapes at all. */
+    fn string(&mut self) -> Result<String, ThemeError> {
+        let quote = self.bump().unwrap();
+        let mut s = String::new();
+        loop {
+            // Check for the end of the line before moving past it, so the
+            // error has the right line number
+            match self.peek() {
+                Some('\n') | None => return self.error("unterminated string".to_string()),
+                _ => (),
+            }
+            match self.bump() {
+                Some(ch) if ch == quote => return Ok(s),
+                Some('\\') if quote == '"' => {
+                    let escaped = match self.bump() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\x08',
+                        Some('f') => '\x0c',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => try!(self.unicode_escape()),
+                        ch => return self.error(format!("unknown escape {}", describe(ch))),
+                    };
+                    s.push(escaped);
+                }
+                Some(ch) => s.push(ch),
+                None => unreachable!(),
+            }
+        }
+    }
+
+    /** Reads the four hex digits of a `\u` escape. */
+    fn unicode_escape(&mut self) -> Result<char, ThemeError> {
+        let mut code = 0u32;
+        for _ in range(0, 4) {
+            let digit = match self.bump().and_then(|ch| ch.to_digit(16)) {
+                Some(digit) => digit as u32,
+                None => return self.error("\\u needs four hex digits".to_string()),
+            };
+            code = code * 16 + digit;
+        }
+        return match char::from_u32(code) {
+            Some(ch) => Ok(ch),
+            None => self.error(format!("\\u{:04x} isn't a character", code)),
+        };
+    }
+
+    /** Reads a whole number, possibly negative.  Nothing in a theme needs
+     * fractions, so they're an error. */
+    fn integer(&mut self) -> Result<i64, ThemeError> {
+        let mut digits = String::new();
+        if self.peek() == Some('-') {
+            self.bump();
+            digits.push('-');
+        }
+        loop {
+            match self.peek() {
+                Some(ch) if ch.is_digit(10) => {
+                    self.bump();
+                    digits.push(ch);
+                }
+                Some('.') | Some('e') | Some('E') => return self.error("only whole numbers are allowed".to_string()),
+                _ => break,
+            }
+        }
+        return match digits.as_slice().parse::<i64>() {
+            Some(n) => Ok(n),
+            None => self.error(format!("{} isn't a number", digits)),
+        };
+    }
+
+    /** Reads a run of letters, for `true` and friends. */
+    fn word(&mut self) -> String {
+        let mut word = String::new();
+        loop {
+            match self.peek() {
+                Some(ch) if ch.is_alphanumeric() || ch == '_' || ch == '-' => {
+                    self.bump();
+                    word.push(ch);
+                }
+                _ => return word,
+            }
+        }
+    }
+}
+
+
+// -----------------------------------------------------------------------------
+// TOML
+
+/** Parses the subset of TOML a theme needs: `[table.headers]`, `key = value`
+ * lines, strings, integers, booleans, arrays, and comments. */
+fn toml_document(scanner: &mut Scanner) -> Result<Vec<(String, Node)>, ThemeError> {
+    let mut root = vec![];
+    let mut path: Vec<String> = vec![];
+    let mut headers: Vec<Vec<String>> = vec![];
+
+    loop {
+        scanner.skip_whitespace(true);
+        let line = scanner.line;
+        match scanner.peek() {
+            None => break,
+            Some('[') => {
+                scanner.bump();
+                scanner.skip_spaces();
+                path = try!(toml_key_path(scanner));
+                scanner.skip_spaces();
+                try!(scanner.expect(']'));
+
+                if headers.contains(&path) {
+                    return Err(ThemeError::Parse(line, format!("[{}] appears twice", path.connect("."))));
+                }
+                headers.push(path.clone());
+                try!(table_at(&mut root, path.as_slice(), line));
+            }
+            Some(_) => {
+                let key = try!(toml_key(scanner));
+                scanner.skip_spaces();
+                try!(scanner.expect('='));
+                scanner.skip_spaces();
+                let value = try!(toml_value(scanner));
+                let table = try!(table_at(&mut root, path.as_slice(), line));
+                try!(insert(table, key, value));
+            }
+        }
+
+        // Nothing else is allowed on the line but a comment
+        scanner.skip_spaces();
+        scanner.skip_comment();
+        match scanner.peek() {
+            Some('\n') | None => (),
+            ch => return scanner.error(format!("expected end of line, found {}", describe(ch))),
+        }
+    }
+
+    return Ok(root);
+}
+
+/** Finds the table at the given path, creating it and any parents as
+ * necessary. */
+fn table_at<'a>(table: &'a mut Vec<(String, Node)>, path: &[String], line: usize) -> Result<&'a mut Vec<(String, Node)>, ThemeError> {
+    if path.is_empty() {
+        return Ok(table);
+    }
+
+    let index = match table.iter().position(|&(ref key, _)| *key == path[0]) {
+        Some(index) => index,
+        None => {
+            table.push((path[0].clone(), Node{ line: line, value: Value::Table(vec![]) }));
+            table.len() - 1
+        }
+    };
+    return match &mut table[index] {
+        &mut (_, Node{ value: Value::Table(ref mut child), .. }) => table_at(child, path.slice_from(1), line),
+        _ => Err(ThemeError::Parse(line, format!("{} is already set to something other than a table", path[0]))),
+    };
+}
+
+fn toml_key_path(scanner: &mut Scanner) -> Result<Vec<String>, ThemeError> {
+    let mut path = vec![try!(toml_key(scanner))];
+    loop {
+        scanner.skip_spaces();
+        if scanner.peek() != Some('.') {
+            return Ok(path);
+        }
+        scanner.bump();
+        scanner.skip_spaces();
+        path.push(try!(toml_key(scanner)));
+    }
+}
+
+/** Reads a key, which is either bare letters, digits, underscores, and
+ * hyphens, or a quoted string. */
+fn toml_key(scanner: &mut Scanner) -> Result<String, ThemeError> {
+    match scanner.peek() {
+        Some('"') | Some('\'') => return scanner.string(),
+        _ => (),
+    }
+    let key = scanner.word();
+    if key.is_empty() {
+        return scanner.error(format!("expected a key, found {}", describe(scanner.peek())));
+    }
+    return Ok(key);
+}
+
+fn toml_value(scanner: &mut Scanner) -> Result<Node, ThemeError> {
+    let line = scanner.line;
+    let value = match scanner.peek() {
+        Some('"') | Some('\'') => Value::String(try!(scanner.string())),
+        Some('[') => {
+            scanner.bump();
+            let mut items = vec![];
+            loop {
+                // Arrays can span lines, and have comments in the middle
+                scanner.skip_whitespace(true);
+                if scanner.peek() == Some(']') {
+                    scanner.bump();
+                    break;
+                }
+                items.push(try!(toml_value(scanner)));
+                scanner.skip_whitespace(true);
+                if scanner.peek() == Some(',') {
+                    scanner.bump();
+                    continue;
+                }
+                try!(scanner.expect(']'));
+                break;
+            }
+            Value::Array(items)
+        }
+        Some(ch) if ch == '-' || ch == '+' || ch.is_digit(10) => {
+            if ch == '+' {
+                scanner.bump();
+            }
+            Value::Integer(try!(scanner.integer()))
+        }
+        Some(ch) if ch.is_alphabetic() => {
+            let word = scanner.word();
+            match word.as_slice() {
+                "true" => Value::Boolean(true),
+                "false" => Value::Boolean(false),
+                _ => return scanner.error(format!("strings need quotes: {}", word)),
+            }
+        }
+        ch => return scanner.error(format!("expected a value, found {}", describe(ch))),
+    };
+    return Ok(Node{ line: line, value: value });
+}
+
+
+// -----------------------------------------------------------------------------
+// JSON
+
+fn json_value(scanner: &mut Scanner) -> Result<Node, ThemeError> {
+    scanner.skip_whitespace(false);
+    let line = scanner.line;
+    let value = match scanner.peek() {
+        Some('{') => {
+            scanner.bump();
+            let mut entries = vec![];
+            scanner.skip_whitespace(false);
+            if scanner.peek() == Some('}') {
+                scanner.bump();
+            }
+            else {
+                loop {
+                    scanner.skip_whitespace(false);
+                    if scanner.peek() != Some('"') {
+                        return scanner.error(format!("expected a key, found {}", describe(scanner.peek())));
+                    }
+                    let key = try!(scanner.string());
+                    scanner.skip_whitespace(false);
+                    try!(scanner.expect(':'));
+                    let value = try!(json_value(scanner));
+                    try!(insert(&mut entries, key, value));
+
+                    scanner.skip_whitespace(false);
+                    if scanner.peek() == Some(',') {
+                        scanner.bump();
+                        continue;
+                    }
+                    try!(scanner.expect('}'));
+                    break;
+                }
+            }
+            Value::Table(entries)
+        }
+        Some('[') => {
+            scanner.bump();
+            let mut items = vec![];
+            scanner.skip_whitespace(false);
+            if scanner.peek() == Some(']') {
+                scanner.bump();
+            }
+            else {
+                loop {
+                    items.push(try!(json_value(scanner)));
+                    scanner.skip_whitespace(false);
+                    if scanner.peek() == Some(',') {
+                        scanner.bump();
+                        continue;
+                    }
+                    try!(scanner.expect(']'));
+                    break;
+                }
+            }
+            Value::Array(items)
+        }
+        Some('"') => Value::String(try!(scanner.string())),
+        Some(ch) if ch == '-' || ch.is_digit(10) => Value::Integer(try!(scanner.integer())),
+        Some(ch) if ch.is_alphabetic() => {
+            let word = scanner.word();
+            match word.as_slice() {
+                "true" => Value::Boolean(true),
+                "false" => Value::Boolean(false),
+                "null" => return scanner.error("null isn't allowed in a theme".to_string()),
+                _ => return scanner.error(format!("strings need quotes: {}", word)),
+            }
+        }
+        ch => return scanner.error(format!("expected a value, found {}", describe(ch))),
+    };
+    return Ok(Node{ line: line, value: value });
+}
@@ -0,0 +1,827 @@
+/** Terminfo's parameterized string language, and the padding that can turn up
+ * in the results.
+ *
+ * This does the job of curses' tparm(3) and tputs(3), except that the output
+ * can go to any Writer rather than wherever C's stdout happens to point.  See
+ * terminfo(5), "Parameterized Strings", for the language itself.
+ */
+
+use std::cmp::max;
+use std::io::{IoResult,Writer};
+use std::io::timer;
+use std::time::Duration;
+
+
+/** A single argument to a capability.  Almost everything takes numbers, but a
+ * handful of capabilities (e.g. `pfkey`) take strings. */
+#[derive(Clone, PartialEq, Show)]
+pub enum Param {
+    Number(isize),
+    Str(String),
+}
+
+#[derive(Clone, PartialEq, Show)]
+pub enum TparmError {
+    /** Tried to pop from an empty stack. */
+    StackUnderflow,
+    /** Expected a number and got a string, or vice versa. */
+    TypeMismatch,
+    /** Something after a % that isn't an operator. */
+    UnknownOperator(char),
+    /** The string ended in the middle of an operator. */
+    Truncated,
+    /** A `%{}` or `%''` constant that isn't one, or is too big. */
+    BadConstant,
+}
+
+
+/** Static variables, `%PA` through `%PZ`.  Unlike the dynamic ones, these
+ * persist from one capability to the next, so the terminal should own one of
+ * these and pass it along every time. */
+pub struct Variables {
+    static_vars: Vec<Param>,
+}
+
+impl Variables {
+    pub fn new() -> Variables {
+        return Variables{ static_vars: blank_vars() };
+    }
+}
+
+fn blank_vars() -> Vec<Param> {
+    return range(0, 26).map(|_| Param::Number(0)).collect();
+}
+
+
+/** Formats a capability string with the given parameters.
+ *
+ * As with C tparm, missing parameters are zero.  No capability uses more than
+ * nine.
+ */
+pub fn tparm(template: &[u8], params: &[Param], vars: &mut Variables) -> Result<Vec<u8>, TparmError> {
+    let program = try!(Program::compile(template));
+    return program.expand(params, vars);
+}
+
+
+/** A single step of a compiled capability. */
+#[derive(Clone, Show)]
+enum Op {
+    /** Plain bytes to copy to the output. */
+    Literal(Vec<u8>),
+    /** %c */
+    OutputChar,
+    /** %d, %s, and their fancier printf-style friends. */
+    Output(FormatSpec),
+    /** %p1 through %p9, zero-indexed. */
+    PushParam(usize),
+    /** %'x' and %{42}. */
+    PushNumber(isize),
+    /** %l */
+    StrLen,
+    /** %Px, with the variable name. */
+    SetVar(u8),
+    /** %gx, with the variable name. */
+    GetVar(u8),
+    /** Arithmetic, logic, and comparison; holds the operator character. */
+    Binary(u8),
+    /** %! */
+    Not,
+    /** %~ */
+    Complement,
+    /** %i */
+    Increment,
+    /** %t: pops a condition, and jumps to the given op if it's false. */
+    JumpUnless(usize),
+    /** %e: reached at the end of a true branch; jumps past the %;. */
+    Jump(usize),
+}
+
+/** A capability string that's been parsed, and can be formatted any number of
+ * times without parsing it again. */
+#[derive(Clone, Show)]
+pub struct Program {
+    ops: Vec<Op>,
+}
+
+/** Bookkeeping for one %? ... %; while compiling. */
+struct Conditional {
+    /** The %t whose jump target isn't known yet, if any. */
+    pending_then: Option<usize>,
+    /** Every %e so far, all of which jump to the %;. */
+    elses: Vec<usize>,
+}
+
+impl Program {
+    /** Parses a capability string.  Errors here are problems with the string
+     * itself; errors from expand() are problems with the arguments. */
+    pub fn compile(template: &[u8]) -> Result<Program, TparmError> {
+        let mut ops = vec![];
+        let mut literal = vec![];
+        let mut conditionals: Vec<Conditional> = vec![];
+
+        let mut i = 0;
+        while i < template.len() {
+            let ch = template[i];
+            i += 1;
+            if ch != b'%' {
+                literal.push(ch);
+                continue;
+            }
+
+            if i >= template.len() {
+                return Err(TparmError::Truncated);
+            }
+            let op = template[i];
+            i += 1;
+
+            if op == b'%' {
+                literal.push(b'%');
+                continue;
+            }
+            if ! literal.is_empty() {
+                ops.push(Op::Literal(literal));
+                literal = vec![];
+            }
+
+            match op {
+                // Output
+                b'c' => ops.push(Op::OutputChar),
+                b'd' | b'o' | b'x' | b'X' | b's' => {
+                    ops.push(Op::Output(FormatSpec{ conversion: op, ..NO_FORMAT }));
+                }
+                b':' | b'#' | b' ' | b'.' | b'0'...b'9' => {
+                    let (spec, next) = try!(parse_format_spec(template, i - 1));
+                    i = next;
+                    ops.push(Op::Output(spec));
+                }
+
+                // Pushing things
+                b'p' => {
+                    if i >= template.len() {
+                        return Err(TparmError::Truncated);
+                    }
+                    let n = template[i];
+                    i += 1;
+                    if n < b'1' || n > b'9' {
+                        return Err(TparmError::UnknownOperator(n as char));
+                    }
+                    ops.push(Op::PushParam((n - b'1') as usize));
+                }
+                b'\'' => {
+                    // Character constant, like %'x'
+                    if i + 1 >= template.len() {
+                        return Err(TparmError::Truncated);
+                    }
+                    if template[i + 1] != b'\'' {
+                        return Err(TparmError::BadConstant);
+                    }
+                    ops.push(Op::PushNumber(template[i] as isize));
+                    i += 2;
+                }
+                b'{' => {
+                    // Integer constant, like %{42}
+                    let mut n = 0is;
+                    loop {
+                        if i >= template.len() {
+                            return Err(TparmError::Truncated);
+                        }
+                        let digit = template[i];
+                        i += 1;
+                        if digit == b'}' {
+                            break;
+                        }
+                        if digit < b'0' || digit > b'9' {
+                            return Err(TparmError::BadConstant);
+                        }
+                        n = match n.checked_mul(10).and_then(|n| n.checked_add((digit - b'0') as isize)) {
+                            Some(n) => n,
+                            None => return Err(TparmError::BadConstant),
+                        };
+                    }
+                    ops.push(Op::PushNumber(n));
+                }
+                b'l' => ops.push(Op::StrLen),
+
+                // Variables
+                b'P' | b'g' => {
+                    if i >= template.len() {
+                        return Err(TparmError::Truncated);
+                    }
+                    let name = template[i];
+                    i += 1;
+                    match name {
+                        b'a'...b'z' | b'A'...b'Z' => (),
+                        _ => return Err(TparmError::UnknownOperator(name as char)),
+                    }
+                    if op == b'P' {
+                        ops.push(Op::SetVar(name));
+                    }
+                    else {
+                        ops.push(Op::GetVar(name));
+                    }
+                }
+
+                // Arithmetic and friends
+                b'+' | b'-' | b'*' | b'/' | b'm'
+                | b'&' | b'|' | b'^'
+                | b'=' | b'>' | b'<' | b'A' | b'O' => ops.push(Op::Binary(op)),
+                b'!' => ops.push(Op::Not),
+                b'~' => ops.push(Op::Complement),
+                b'i' => ops.push(Op::Increment),
+
+                // Conditionals: %? cond %t then %e else %;
+                // Else-if chains are written %? c1 %t b1 %e c2 %t b2 %e b3 %;
+                // so a false condition jumps just past the next %e, and every
+                // %e jumps to the end.
+                b'?' => {
+                    conditionals.push(Conditional{ pending_then: None, elses: vec![] });
+                }
+                b't' => {
+                    // curses doesn't actually care whether there was a %?
+                    if conditionals.is_empty() {
+                        conditionals.push(Conditional{ pending_then: None, elses: vec![] });
+                    }
+                    let cond = conditionals.last_mut().unwrap();
+                    cond.pending_then = Some(ops.len());
+                    ops.push(Op::JumpUnless(0));
+                }
+                b'e' => {
+                    if conditionals.is_empty() {
+                        conditionals.push(Conditional{ pending_then: None, elses: vec![] });
+                    }
+                    let cond = conditionals.last_mut().unwrap();
+                    cond.elses.push(ops.len());
+                    ops.push(Op::Jump(0));
+                    match cond.pending_then.take() {
+                        Some(then) => ops[then] = Op::JumpUnless(ops.len()),
+                        None => (),
+                    }
+                }
+                b';' => {
+                    match conditionals.pop() {
+                        Some(cond) => patch_conditional(&mut ops, cond),
+                        None => (),
+                    }
+                }
+
+                _ => return Err(TparmError::UnknownOperator(op as char)),
+            }
+        }
+
+        if ! literal.is_empty() {
+            ops.push(Op::Literal(literal));
+        }
+        // Anything left unterminated just jumps to the end
+        while let Some(cond) = conditionals.pop() {
+            patch_conditional(&mut ops, cond);
+        }
+
+        return Ok(Program{ ops: ops });
+    }
+
+    /** Returns true if this program doesn't use any parameters or variables,
+     * i.e. it always produces the same output. */
+    pub fn is_constant(&self) -> bool {
+        return self.ops.iter().all(|op| match *op {
+            Op::PushParam(_) | Op::SetVar(_) | Op::GetVar(_) => false,
+            _ => true,
+        });
+    }
+
+    /** Formats this capability with the given parameters. */
+    pub fn expand(&self, params: &[Param], vars: &mut Variables) -> Result<Vec<u8>, TparmError> {
+        let mut out = vec![];
+        let mut stack: Vec<Param> = vec![];
+        let mut dynamic_vars = blank_vars();
+
+        // Copy the parameters, since %i modifies them
+        let mut params = params.to_vec();
+        while params.len() < 9 {
+            params.push(Param::Number(0));
+        }
+
+        let mut pc = 0;
+        while pc < self.ops.len() {
+            let op = &self.ops[pc];
+            pc += 1;
+
+            match *op {
+                Op::Literal(ref bytes) => out.push_all(bytes.as_slice()),
+                Op::OutputChar => {
+                    let n = try!(pop_number(&mut stack));
+                    // Like ncurses, send NUL as 0x80, which most terminals
+                    // treat the same and which doesn't end the string for C
+                    // programs
+                    out.push(if n as u8 == 0 { 0x80 } else { n as u8 });
+                }
+                Op::Output(ref spec) => try!(format_param(&mut out, &mut stack, spec)),
+
+                Op::PushParam(n) => stack.push(params[n].clone()),
+                Op::PushNumber(n) => stack.push(Param::Number(n)),
+                Op::StrLen => {
+                    let s = try!(pop_string(&mut stack));
+                    stack.push(Param::Number(s.len() as isize));
+                }
+
+                Op::SetVar(name) | Op::GetVar(name) => {
+                    let var = match name {
+                        b'a'...b'z' => &mut dynamic_vars[(name - b'a') as usize],
+                        _ => &mut vars.static_vars[(name - b'A') as usize],
+                    };
+                    match *op {
+                        Op::SetVar(_) => *var = try!(pop(&mut stack)),
+                        _ => stack.push(var.clone()),
+                    }
+                }
+
+                Op::Binary(operator) => {
+                    let b = try!(pop_number(&mut stack));
+                    let a = try!(pop_number(&mut stack));
+                    // Overflow wraps, as it does in C, rather than panicking
+                    let result = match operator {
+                        b'+' => a.wrapping_add(b),
+                        b'-' => a.wrapping_sub(b),
+                        b'*' => a.wrapping_mul(b),
+                        // Dividing by zero is zero, as far as curses is concerned
+                        b'/' => if b == 0 { 0 } else if b == -1 { 0is.wrapping_sub(a) } else { a / b },
+                        b'm' => if b == 0 || b == -1 { 0 } else { a % b },
+                        b'&' => a & b,
+                        b'|' => a | b,
+                        b'^' => a ^ b,
+                        b'=' => (a == b) as isize,
+                        b'>' => (a > b) as isize,
+                        b'<' => (a < b) as isize,
+                        b'A' => (a != 0 && b != 0) as isize,
+                        b'O' => (a != 0 || b != 0) as isize,
+                        _ => unreachable!(),
+                    };
+                    stack.push(Param::Number(result));
+                }
+                Op::Not => {
+                    let a = try!(pop_number(&mut stack));
+                    stack.push(Param::Number((a == 0) as isize));
+                }
+                Op::Complement => {
+                    let a = try!(pop_number(&mut stack));
+                    stack.push(Param::Number(!a));
+                }
+                Op::Increment => {
+                    // Make the first two parameters one-based, for ANSI terminals
+                    for param in params.slice_to_mut(2).iter_mut() {
+                        match *param {
+                            Param::Number(ref mut n) => *n = n.wrapping_add(1),
+                            Param::Str(_) => (),
+                        }
+                    }
+                }
+
+                Op::JumpUnless(target) => {
+                    if try!(pop_number(&mut stack)) == 0 {
+                        pc = target;
+                    }
+                }
+                Op::Jump(target) => pc = target,
+            }
+        }
+
+        return Ok(out);
+    }
+}
+
+/** Points a finished conditional's jumps at the op following it. */
+fn patch_conditional(ops: &mut Vec<Op>, cond: Conditional) {
+    let end = ops.len();
+    match cond.pending_then {
+        Some(then) => ops[then] = Op::JumpUnless(end),
+        None => (),
+    }
+    for &jump in cond.elses.iter() {
+        ops[jump] = Op::Jump(end);
+    }
+}
+
+fn pop(stack: &mut Vec<Param>) -> Result<Param, TparmError> {
+    return match stack.pop() {
+        Some(param) => Ok(param),
+        None => Err(TparmError::StackUnderflow),
+    };
+}
+
+fn pop_number(stack: &mut Vec<Param>) -> Result<isize, TparmError> {
+    return match try!(pop(stack)) {
+        Param::Number(n) => Ok(n),
+        Param::Str(_) => Err(TparmError::TypeMismatch),
+    };
+}
+
+fn pop_string(stack: &mut Vec<Param>) -> Result<String, TparmError> {
+    return match try!(pop(stack)) {
+        Param::Str(s) => Ok(s),
+        Param::Number(_) => Err(TparmError::TypeMismatch),
+    };
+}
+
+// ----------------------------------------------------------------------------
+// printf-style formatting
+
+#[derive(Clone, Show)]
+struct FormatSpec {
+    left_justify: bool,
+    plus_sign: bool,
+    space_sign: bool,
+    alternate: bool,
+    zero_pad: bool,
+    width: usize,
+    precision: Option<usize>,
+    conversion: u8,
+}
+
+static NO_FORMAT: FormatSpec = FormatSpec{
+    left_justify: false,
+    plus_sign: false,
+    space_sign: false,
+    alternate: false,
+    zero_pad: false,
+    width: 0,
+    precision: None,
+    conversion: b'd',
+};
+
+/** Parses a spec like `%:-3d` or `%02x`, starting just after the %.  Returns
+ * the spec and the position following it. */
+fn parse_format_spec(template: &[u8], start: usize) -> Result<(FormatSpec, usize), TparmError> {
+    let mut spec = FormatSpec{ ..NO_FORMAT };
+    let mut i = start;
+
+    // - and + are already operators, so flags using them must be preceded by
+    // a colon
+    let mut allow_signs = false;
+    if i < template.len() && template[i] == b':' {
+        allow_signs = true;
+        i += 1;
+    }
+
+    while i < template.len() {
+        match template[i] {
+            b'-' if allow_signs => spec.left_justify = true,
+            b'+' if allow_signs => spec.plus_sign = true,
+            b' ' => spec.space_sign = true,
+            b'#' => spec.alternate = true,
+            _ => break,
+        }
+        i += 1;
+    }
+
+    if i < template.len() && template[i] == b'0' {
+        spec.zero_pad = true;
+        i += 1;
+    }
+    while i < template.len() && template[i] >= b'0' && template[i] <= b'9' {
+        spec.width = spec.width * 10 + (template[i] - b'0') as usize;
+        i += 1;
+    }
+
+    if i < template.len() && template[i] == b'.' {
+        i += 1;
+        let mut precision = 0;
+        while i < template.len() && template[i] >= b'0' && template[i] <= b'9' {
+            precision = precision * 10 + (template[i] - b'0') as usize;
+            i += 1;
+        }
+        spec.precision = Some(precision);
+    }
+
+    if i >= template.len() {
+        return Err(TparmError::Truncated);
+    }
+    match template[i] {
+        b'd' | b'o' | b'x' | b'X' | b's' => spec.conversion = template[i],
+        other => return Err(TparmError::UnknownOperator(other as char)),
+    }
+
+    return Ok((spec, i + 1));
+}
+
+/** Pops a parameter and writes it out according to a format spec. */
+fn format_param(out: &mut Vec<u8>, stack: &mut Vec<Param>, spec: &FormatSpec) -> Result<(), TparmError> {
+    let mut prefix = String::new();
+    let mut body;
+
+    if spec.conversion == b's' {
+        body = try!(pop_string(stack));
+        match spec.precision {
+            Some(precision) if precision < body.len() => body.truncate(precision),
+            _ => (),
+        }
+    }
+    else {
+        let n = try!(pop_number(stack));
+        // C treats anything but %d as unsigned
+        let unsigned = n as u32;
+        body = match spec.conversion {
+            b'o' => format!("{:o}", unsigned),
+            b'x' => format!("{:x}", unsigned),
+            b'X' => format!("{:X}", unsigned),
+            // The sign goes in the prefix.  Negating would overflow for
+            // the most negative number, so chop it off the text instead
+            _ => {
+                let digits = format!("{}", n);
+                if n < 0 { digits.slice_from(1).to_string() } else { digits }
+            }
+        };
+
+        match spec.precision {
+            Some(precision) if precision > body.len() => {
+                let zeroes: String = range(body.len(), precision).map(|_| '0').collect();
+                body = zeroes + body.as_slice();
+            }
+            _ => (),
+        }
+
+        if spec.conversion == b'd' {
+            if n < 0 {
+                prefix.push('-');
+            }
+            else if spec.plus_sign {
+                prefix.push('+');
+            }
+            else if spec.space_sign {
+                prefix.push(' ');
+            }
+        }
+        else if spec.alternate && n != 0 {
+            match spec.conversion {
+                b'o' if ! body.starts_with("0") => prefix.push('0'),
+                b'x' => prefix.push_str("0x"),
+                b'X' => prefix.push_str("0X"),
+                _ => (),
+            }
+        }
+    }
+
+    let len = prefix.len() + body.len();
+    let padding = if spec.width > len { spec.width - len } else { 0 };
+    if spec.left_justify {
+        out.push_all(prefix.as_bytes());
+        out.push_all(body.as_bytes());
+        out.extend(range(0, padding).map(|_| b' '));
+    }
+    else if spec.zero_pad && spec.precision.is_none() && spec.conversion != b's' {
+        out.push_all(prefix.as_bytes());
+        out.extend(range(0, padding).map(|_| b'0'));
+        out.push_all(body.as_bytes());
+    }
+    else {
+        out.extend(range(0, padding).map(|_| b' '));
+        out.push_all(prefix.as_bytes());
+        out.push_all(body.as_bytes());
+    }
+
+    return Ok(());
+}
+
+
+// ----------------------------------------------------------------------------
+// Padding
+
+/** Everything needed to honor `$<...>` delays, which ancient terminals needed
+ * to keep up with the host. */
+#[derive(Clone, Show)]
+pub struct Padding {
+    /** Output speed, in bits per second.  Zero if unknown. */
+    pub baud_rate: usize,
+    /** Below this speed, padding isn't needed at all (the `pb` capability). */
+    pub padding_baud_rate: usize,
+    /** What to pad with (the `pad` capability, or NUL by default), or None
+     * if the terminal can't be padded with characters (`npc`).  In that case
+     * we flush and sleep instead. */
+    pub pad_char: Option<u8>,
+    /** Whether the terminal has XON/XOFF flow control (`xon`), which makes
+     * any padding not marked as mandatory unnecessary. */
+    pub xon: bool,
+}
+
+/** Writes a formatted capability, expanding any padding.
+ *
+ * `affected_lines` is how many lines the capability touches, for delays that
+ * are proportional to it, like `$<5*>`.
+ */
+pub fn tputs<W: Writer>(out: &mut W, s: &[u8], affected_lines: usize, padding: &Padding) -> IoResult<()> {
+    let mut start = 0;
+    let mut i = 0;
+    while i + 1 < s.len() {
+        if s[i] == b'$' && s[i + 1] == b'<' {
+            match parse_delay(s, i + 2) {
+                Some((delay, next)) => {
+                    try!(out.write(s.slice(start, i)));
+                    try!(write_delay(out, &delay, affected_lines, padding));
+                    start = next;
+                    i = next;
+                    continue;
+                }
+                // Not actually a delay, so leave it alone
+                None => (),
+            }
+        }
+        i += 1;
+    }
+
+    return out.write(s.slice_from(start));
+}
+
+struct Delay {
+    /** In tenths of a millisecond, which is as fine as terminfo gets. */
+    tenths: usize,
+    proportional: bool,
+    mandatory: bool,
+}
+
+/** Parses the inside of `$<...>`, starting just after the `<`.  Returns the
+ * delay and the position following the `>`. */
+fn parse_delay(s: &[u8], start: usize) -> Option<(Delay, usize)> {
+    let mut delay = Delay{ tenths: 0, proportional: false, mandatory: false };
+    let mut i = start;
+
+    let mut seen_digit = false;
+    while i < s.len() && s[i] >= b'0' && s[i] <= b'9' {
+        delay.tenths = delay.tenths * 10 + (s[i] - b'0') as usize;
+        seen_digit = true;
+        i += 1;
+    }
+    delay.tenths *= 10;
+
+    if i < s.len() && s[i] == b'.' {
+        i += 1;
+        // Only one decimal place is meaningful; ignore the rest
+        if i < s.len() && s[i] >= b'0' && s[i] <= b'9' {
+            delay.tenths += (s[i] - b'0') as usize;
+            seen_digit = true;
+        }
+        while i < s.len() && s[i] >= b'0' && s[i] <= b'9' {
+            i += 1;
+        }
+    }
+
+    if ! seen_digit {
+        return None;
+    }
+
+    while i < s.len() {
+        match s[i] {
+            b'*' => delay.proportional = true,
+            b'/' => delay.mandatory = true,
+            b'>' => return Some((delay, i + 1)),
+            _ => return None,
+        }
+        i += 1;
+    }
+
+    return None;
+}
+
+fn write_delay<W: Writer>(out: &mut W, delay: &Delay, affected_lines: usize, padding: &Padding) -> IoResult<()> {
+    if ! delay.mandatory && (padding.xon || padding.baud_rate < padding.padding_baud_rate) {
+        return Ok(());
+    }
+    // Without a baud rate there's no telling how long the terminal needs, so
+    // do as ncurses does and don't pad at all, rather than stall the output
+    if padding.baud_rate == 0 {
+        return Ok(());
+    }
+
+    let mut tenths = delay.tenths;
+    if delay.proportional {
+        tenths *= max(affected_lines, 1);
+    }
+    if tenths == 0 {
+        return Ok(());
+    }
+
+    match padding.pad_char {
+        Some(pad_char) => {
+            // About ten bits go over the wire per character; round up
+            let count = (tenths * padding.baud_rate + 99999) / 100000;
+            let pad: Vec<u8> = range(0, count).map(|_| pad_char).collect();
+            return out.write(pad.as_slice());
+        }
+        None => {
+            try!(out.flush());
+            timer::sleep(Duration::microseconds(tenths as i64 * 100));
+            return Ok(());
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::isize;
+    use super::{Padding,Param,TparmError,Variables,parse_delay,tparm,tputs};
+
+    fn format(template: &str, params: &[isize]) -> Result<Vec<u8>, TparmError> {
+        let params: Vec<Param> = params.iter().map(|&n| Param::Number(n)).collect();
+        return tparm(template.as_bytes(), params.as_slice(), &mut Variables::new());
+    }
+
+    fn formatted(template: &str, params: &[isize]) -> String {
+        return String::from_utf8(format(template, params).ok().expect("should format")).unwrap();
+    }
+
+    #[test]
+    fn params_and_increment() {
+        assert_eq!(formatted("%p1%d", &[42]), "42");
+        assert_eq!(formatted("%p1%d", &[-7]), "-7");
+        assert_eq!(formatted("%p2%d,%p1%d", &[1, 2]), "2,1");
+        // Missing parameters are zero
+        assert_eq!(formatted("%p3%d", &[1]), "0");
+        // The usual cup
+        assert_eq!(formatted("\x1b[%i%p1%d;%p2%dH", &[4, 9]), "\x1b[5;10H");
+        assert_eq!(formatted("%p1%03d|%p1%:-4d|%p1%x", &[26]), "026|26  |1a");
+    }
+
+    #[test]
+    fn conditionals() {
+        let template = "%?%p1%{1}%=%tone%e%p1%{2}%=%ttwo%e%?%p2%tthree-a%ethree-b%;%;!";
+        assert_eq!(formatted(template, &[1, 0]), "one!");
+        assert_eq!(formatted(template, &[2, 0]), "two!");
+        assert_eq!(formatted(template, &[3, 1]), "three-a!");
+        assert_eq!(formatted(template, &[3, 0]), "three-b!");
+        // A % as a character constant inside a skipped branch
+        assert_eq!(formatted("%?%p1%t%'%'%c%;x", &[0]), "x");
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(formatted("%{42}%d", &[]), "42");
+        assert_eq!(formatted("%'A'%d", &[]), "65");
+        assert_eq!(format("%{-1}%d", &[]), Err(TparmError::BadConstant));
+        assert_eq!(format("%{x}%d", &[]), Err(TparmError::BadConstant));
+        assert_eq!(format("%{99999999999999999999999}%d", &[]), Err(TparmError::BadConstant));
+        assert_eq!(format("%{42", &[]), Err(TparmError::Truncated));
+        assert_eq!(format("%'xy'%d", &[]), Err(TparmError::BadConstant));
+        assert_eq!(format("%'x", &[]), Err(TparmError::Truncated));
+    }
+
+    #[test]
+    fn overflow_wraps() {
+        let template = format!("%{{{}}}%{{1}}%+%d", isize::MAX);
+        assert_eq!(formatted(template.as_slice(), &[]), format!("{}", isize::MIN));
+        assert_eq!(formatted("%p1%d", &[isize::MIN]), format!("{}", isize::MIN));
+        assert_eq!(formatted("%p1%{0}%{1}%-%/%d", &[isize::MIN]), format!("{}", isize::MIN));
+    }
+
+    #[test]
+    fn char_output() {
+        assert_eq!(format("%p1%c", &[65]), Ok(vec![b'A']));
+        assert_eq!(format("%p1%c", &[0]), Ok(vec![0x80]));
+    }
+
+    #[test]
+    fn delay_parsing() {
+        let (delay, next) = parse_delay(b"5*/>rest", 0).expect("should parse");
+        assert_eq!(delay.tenths, 50);
+        assert!(delay.proportional);
+        assert!(delay.mandatory);
+        assert_eq!(next, 4);
+
+        let (delay, _) = parse_delay(b"2.57>", 0).expect("should parse");
+        assert_eq!(delay.tenths, 25);
+        assert!(! delay.proportional);
+
+        assert!(parse_delay(b">", 0).is_none());
+        assert!(parse_delay(b"5x>", 0).is_none());
+        assert!(parse_delay(b"5", 0).is_none());
+    }
+
+    #[test]
+    fn padding() {
+        let mut padding = Padding{ baud_rate: 9600, padding_baud_rate: 0, pad_char: Some(0), xon: false };
+
+        // 5ms per line, over two lines, is 10ms, or about ten characters at
+        // 9600 baud
+        let mut out = vec![];
+        tputs(&mut out, b"a$<5*/>b", 2, &padding).unwrap();
+        let mut expected = vec![b'a'];
+        expected.extend(range(0, 10).map(|_| 0u8));
+        expected.push(b'b');
+        assert_eq!(out, expected);
+
+        // Not a delay at all
+        let mut out = vec![];
+        tputs(&mut out, b"$<x>", 1, &padding).unwrap();
+        assert_eq!(out, b"$<x>".to_vec());
+
+        // Flow control makes non-mandatory padding pointless
+        padding.xon = true;
+        let mut out = vec![];
+        tputs(&mut out, b"a$<5>b", 1, &padding).unwrap();
+        assert_eq!(out, b"ab".to_vec());
+
+        // And without a baud rate, there's no padding at all
+        padding.baud_rate = 0;
+        let mut out = vec![];
+        tputs(&mut out, b"a$<5/>b", 1, &padding).unwrap();
+        assert_eq!(out, b"ab".to_vec());
+    }
+}
@@ -7,7 +7,7 @@
 extern crate amulet;
 
 fn main() {
-    let term = amulet::Terminal::new();
+    let term = amulet::Terminal::new().unwrap();
 
     term.at(0, term.height() - 1, |&:| {
         term.write("This is ");
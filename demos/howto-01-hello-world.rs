@@ -7,7 +7,7 @@
 extern crate amulet;
 
 fn main() {
-    let mut term = amulet::Terminal::new();
+    let mut term = amulet::Terminal::new().unwrap();
     let mut canvas = term.enter_fullscreen();
     canvas.write("Hello World !!!");
     canvas.repaint();
@@ -9,7 +9,7 @@ extern crate amulet;
 fn main() {
     let bold = amulet::ll::Style().bold();
 
-    let mut term = amulet::Terminal::new();
+    let mut term = amulet::Terminal::new().unwrap();
     let mut canvas = term.enter_fullscreen();
     // TODO implement me -- right now there's NEVER echo, so
     // TODO also i don't like this curses-style api; maybe a "set_options"
@@ -9,7 +9,7 @@ extern crate amulet;
 fn main() {
     let mesg = "Enter a string: ";
 
-    let mut term = amulet::Terminal::new();
+    let mut term = amulet::Terminal::new().unwrap();
     let mut canvas = term.enter_fullscreen();
     let (rows, cols) = canvas.size();
 
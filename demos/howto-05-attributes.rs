@@ -35,7 +35,7 @@ fn main() {
 
     // cannot open input file...
 
-    let mut term = amulet::Terminal::new();
+    let mut term = amulet::Terminal::new().unwrap();
     let mut canvas = term.enter_fullscreen();
     let mut ch : u8;
     let (rows, _cols) = canvas.size();
@@ -6,10 +6,11 @@
 
 extern crate amulet;
 
+use amulet::color::Color;
 use amulet::ll::Style;
 
 fn main() {
-    let mut term = amulet::Terminal::new();
+    let mut term = amulet::Terminal::new().unwrap();
     let mut canvas = term.enter_fullscreen();
 
     canvas.write("A big string which I didn't care to type fully");
@@ -18,7 +19,7 @@ fn main() {
     // TODO the original curses function also takes an argument for how many
     // characters to change, with -1 meaning "to end of line" (which i am not
     // in love with)
-    canvas.restyle(Style().fg(13));
+    canvas.restyle(Style().fg(Color::BrightMagenta));
 
     canvas.repaint();
     canvas.pause();
@@ -9,7 +9,7 @@ extern crate amulet;
 use amulet::canvas::Canvas;
 
 fn main() {
-    let mut term = amulet::Terminal::new();
+    let mut term = amulet::Terminal::new().unwrap();
     let mut canvas = term.enter_fullscreen();
 
     let height = 3;
@@ -57,7 +57,7 @@ fn main() {
 }
 
 fn create_newwin<'a, 'b>(canvas: &Canvas<'a, 'b>, height: usize, width: usize, starty: usize, startx: usize) -> Canvas<'a, 'b> {
-    let mut local_win = canvas.spawn(startx, starty, width, height);
+    let mut local_win = canvas.spawn(starty, startx, height, width);
     // 0,0 gives default chars for the vertical and horizontal lines
     //local_win.set_box(0 as char, 0 as char);
     // TODO: box borders don't belong on Canvas since they are more a UI thing.